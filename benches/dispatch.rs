@@ -0,0 +1,103 @@
+//! Compares the monomorphized dispatch of `CPU::next` against the same core
+//! driven through `dyn Bus`/`dyn IoBus`, and against memory behind a
+//! `dyn IndexMut<u16>`, which is how every access went before `Bus`.
+//!
+//! Run with `cargo bench --bench dispatch`.
+use i8080_core::cpu::bus::{Bus, Indexed};
+use i8080_core::cpu::io::{IoBus, Ports};
+use i8080_core::cpu::CPU;
+use std::hint::black_box;
+use std::ops::{Index, IndexMut};
+use std::time::{Duration, Instant};
+
+const STEPS: u32 = 20_000_000;
+/// Sums into 8000H..80FFH, 256 bytes at a time, away from the code and stack.
+const PROGRAM: [u8; 21] = [
+    0x31, 0x00, 0x00, // LXI SP,0000H
+    0x21, 0x00, 0x80, // OUTER: LXI H,8000H
+    0x06, 0x00, //       MVI B,00H
+    0x7E, //             LOOP: MOV A,M
+    0x80, //             ADD B
+    0x77, //             MOV M,A
+    0x23, //             INX H
+    0xE5, //             PUSH H
+    0xD1, //             POP D
+    0x05, //             DCR B
+    0xC2, 0x08, 0x00, // JNZ LOOP
+    0xC3, 0x03, 0x00, // JMP OUTER
+];
+
+fn machine() -> (CPU, Box<[u8; 0x10000]>, Ports) {
+    let mut mem = Box::new([0u8; 0x10000]);
+    mem[..PROGRAM.len()].copy_from_slice(&PROGRAM);
+    (CPU::new(None, None), mem, Ports::new())
+}
+
+fn run<M: Bus + ?Sized, I: IoBus + ?Sized>(cpu: &mut CPU, mem: &mut M, io: &mut I) -> Duration {
+    let start = Instant::now();
+    let mut cycles = 0u64;
+    for _ in 0..STEPS {
+        cycles += cpu.next(mem, io) as u64;
+    }
+    black_box(cycles);
+    start.elapsed()
+}
+
+/// Makes sure the timed steps were the loop, not HLT or fault idling.
+fn check(cpu: &CPU, mem: &[u8; 0x10000]) {
+    assert_eq!(mem[..PROGRAM.len()], PROGRAM, "program overwritten");
+    assert!(!cpu.is_halted(), "halted at {:04X}", cpu.regs.pc);
+    assert!(cpu.regs.pc < PROGRAM.len() as u16, "ran off to {:04X}", cpu.regs.pc);
+}
+
+/// Memory reached through a `dyn IndexMut<u16>`, as the core took it originally.
+struct DynIndex<'a>(&'a mut dyn IndexMut<u16, Output = u8>);
+impl Index<u16> for DynIndex<'_> {
+    type Output = u8;
+    fn index(&self, addr: u16) -> &u8 {
+        &self.0[addr]
+    }
+}
+impl IndexMut<u16> for DynIndex<'_> {
+    fn index_mut(&mut self, addr: u16) -> &mut u8 {
+        &mut self.0[addr]
+    }
+}
+struct Ram(Box<[u8; 0x10000]>);
+impl Index<u16> for Ram {
+    type Output = u8;
+    fn index(&self, addr: u16) -> &u8 {
+        &self.0[addr as usize]
+    }
+}
+impl IndexMut<u16> for Ram {
+    fn index_mut(&mut self, addr: u16) -> &mut u8 {
+        &mut self.0[addr as usize]
+    }
+}
+
+fn report(name: &str, elapsed: Duration, generic: Duration) {
+    let mips = STEPS as f64 / elapsed.as_secs_f64() / 1e6;
+    let ratio = elapsed.as_secs_f64() / generic.as_secs_f64();
+    println!("{name:>14}: {elapsed:>10.2?} for {STEPS} instructions ({mips:.1} M instr/s, {ratio:.2}x generic)");
+}
+
+fn main() {
+    let (mut cpu, mut mem, mut io) = machine();
+    let generic = run(&mut cpu, &mut *mem, &mut io);
+    check(&cpu, &mem);
+
+    let (mut cpu, mut mem, mut io) = machine();
+    let dynamic = run(&mut cpu, black_box(&mut *mem as &mut dyn Bus), black_box(&mut io as &mut dyn IoBus));
+    check(&cpu, &mem);
+
+    let (mut cpu, mem, mut io) = machine();
+    let mut ram = Ram(mem);
+    let mut bus = Indexed(DynIndex(black_box(&mut ram as &mut dyn IndexMut<u16, Output = u8>)));
+    let index_mut = run(&mut cpu, &mut bus, &mut io);
+    check(&cpu, &ram.0);
+
+    report("generic", generic, generic);
+    report("dyn Bus", dynamic, generic);
+    report("dyn IndexMut", index_mut, generic);
+}
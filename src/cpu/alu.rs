@@ -0,0 +1,81 @@
+//! Flag computation for the 8-bit ALU. Every arithmetic and logical handler
+//! goes through these functions, so each flag rule lives in one place.
+use super::Variant;
+/// Result byte plus the carry, auxiliary carry and two's-complement overflow
+/// an operation produced. S, Z and P follow from `value`. `overflow` is `None`
+/// for operations that leave the 8085 V and K flags alone.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct AluResult {
+    pub value: u8,
+    pub carry: bool,
+    pub aux: bool,
+    pub overflow: Option<bool>,
+}
+/// ADD/ADC/ADI/ACI: AC is the carry out of bit 3.
+pub(crate) fn add(a: u8, b: u8, carry: bool) -> AluResult {
+    let sum = a as u16 + b as u16 + carry as u16;
+    let value = sum as u8;
+    AluResult {
+        value,
+        carry: sum > 0xFF,
+        aux: (a & 0x0F) + (b & 0x0F) + carry as u8 > 0x0F,
+        overflow: Some(!(a ^ b) & (a ^ value) & 0x80 != 0),
+    }
+}
+/// SUB/SBB/SUI/SBI/CMP/CPI. The 8080 adds the complement of `b` with the
+/// inverted borrow, so AC is set when bit 3 does *not* borrow, while CY is
+/// the inverted carry out, i.e. the borrow.
+pub(crate) fn sub(a: u8, b: u8, borrow: bool) -> AluResult {
+    let AluResult { value, carry, aux, .. } = add(a, !b, !borrow);
+    AluResult {
+        value,
+        carry: !carry,
+        aux,
+        overflow: Some((a ^ b) & (a ^ value) & 0x80 != 0),
+    }
+}
+/// INR: like ADD 1 but the caller keeps CY.
+pub(crate) fn inr(r: u8) -> AluResult {
+    add(r, 1, false)
+}
+/// DCR: like SUB 1, so AC is set unless the low nibble was 0.
+pub(crate) fn dcr(r: u8) -> AluResult {
+    sub(r, 1, false)
+}
+/// ANA/ANI: CY is cleared. The 8080 sets AC to the OR of bit 3 of both
+/// operands; the 8085 always sets it.
+pub(crate) fn and(a: u8, b: u8, variant: Variant) -> AluResult {
+    let aux = match variant {
+        Variant::I8080 => (a | b) & 0x08 != 0,
+        #[cfg(feature = "i8085")]
+        Variant::I8085 => true,
+    };
+    AluResult { value: a & b, carry: false, aux, overflow: None }
+}
+/// ORA/ORI: CY and AC cleared.
+pub(crate) fn or(a: u8, b: u8) -> AluResult {
+    AluResult { value: a | b, carry: false, aux: false, overflow: None }
+}
+/// XRA/XRI: CY and AC cleared.
+pub(crate) fn xor(a: u8, b: u8) -> AluResult {
+    AluResult { value: a ^ b, carry: false, aux: false, overflow: None }
+}
+/// DAA: adds 06H if the low digit is over 9 or AC is set, and 60H if the
+/// whole byte is over 99H or CY is set. CY is only ever set, never cleared,
+/// and AC is the carry out of bit 3 of the adjustment.
+pub(crate) fn daa(a: u8, carry: bool, aux: bool) -> AluResult {
+    let mut adjust = 0;
+    if a & 0x0F > 9 || aux {
+        adjust |= 0x06;
+    }
+    let carry = carry || a > 0x99;
+    if carry {
+        adjust |= 0x60;
+    }
+    AluResult {
+        value: a.wrapping_add(adjust),
+        carry,
+        aux: (a & 0x0F) + (adjust & 0x0F) > 0x0F,
+        overflow: None,
+    }
+}
@@ -0,0 +1,550 @@
+//! A two-pass assembler for Intel 8080 source, meant for writing test programs
+//! as text instead of hand-encoded bytes. Instructions are matched against the
+//! same templates `disasm` prints, so whatever the disassembler shows
+//! assembles back to the same bytes.
+//!
+//! Supported: labels (with or without a trailing colon in column 0), `ORG`,
+//! `DB`, `DW`, `DS`, `EQU`, `END`, `$` for the current address, numbers in
+//! decimal, `0FFH`, `0x` hex, `101B` binary and `17O`/`17Q` octal, character
+//! literals, and the operators `+ - * / % & | ^ << >> ~` with their keyword
+//! forms `MOD AND OR XOR SHL SHR NOT HIGH LOW`. Names are case-insensitive.
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use super::bus::Bus;
+use super::disasm::{self, Syntax};
+use super::Variant;
+
+/// Output of `assemble`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assembly {
+    /// Address of `image[0]`, the lowest address anything was emitted at.
+    pub origin: u16,
+    /// Every emitted byte from `origin` on; gaps left by `ORG` and `DS` are zero.
+    pub image: Vec<u8>,
+    /// Labels and `EQU` names, upper-cased.
+    pub symbols: BTreeMap<String, u16>,
+    pub listing: Vec<Line>,
+}
+/// One source line with the address and bytes it produced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    /// 1-based line number.
+    pub number: usize,
+    pub addr: u16,
+    pub bytes: Vec<u8>,
+    pub source: String,
+}
+/// Why `assemble` failed, and on which 1-based line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub kind: ErrorKind,
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Neither an instruction nor a directive.
+    UnknownMnemonic(String),
+    /// The operands fit no form of the mnemonic, e.g. `MOV A` or `LDAX H`.
+    InvalidOperands(String),
+    UndefinedSymbol(String),
+    DuplicateSymbol(String),
+    /// `EQU` without a name.
+    MissingName,
+    /// A value does not fit its byte or word, or code runs past FFFFH.
+    OutOfRange(i32),
+    /// Malformed expression or literal.
+    Syntax(String),
+}
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ErrorKind::UnknownMnemonic(m) => write!(f, "unknown mnemonic {}", m),
+            ErrorKind::InvalidOperands(o) => write!(f, "invalid operands {}", o),
+            ErrorKind::UndefinedSymbol(s) => write!(f, "undefined symbol {}", s),
+            ErrorKind::DuplicateSymbol(s) => write!(f, "duplicate symbol {}", s),
+            ErrorKind::MissingName => write!(f, "EQU needs a name"),
+            ErrorKind::OutOfRange(v) => write!(f, "value {} out of range", v),
+            ErrorKind::Syntax(s) => write!(f, "syntax error at {}", s),
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for AsmError {}
+impl Assembly {
+    /// Writes the image into `mem` starting at `origin`.
+    pub fn load<M: Bus + ?Sized>(&self, mem: &mut M) {
+        for (i, b) in self.image.iter().enumerate() {
+            mem.write(self.origin.wrapping_add(i as u16), *b);
+        }
+    }
+    /// Looks a symbol up by name, ignoring case.
+    pub fn symbol(&self, name: &str) -> Option<u16> {
+        self.symbols.get(&name.to_ascii_uppercase()).copied()
+    }
+}
+impl fmt::Display for Line {
+    /// `0100  21 34 12  LXI H,1234H`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04X} ", self.addr)?;
+        for b in &self.bytes {
+            write!(f, " {:02X}", b)?;
+        }
+        for _ in self.bytes.len()..3 {
+            f.write_str("   ")?;
+        }
+        write!(f, "  {}", self.source)
+    }
+}
+/// Assembles `src` for `variant`; the 8085 adds its own mnemonics.
+pub fn assemble(variant: Variant, src: &str) -> Result<Assembly, AsmError> {
+    let mut asm = Assembler {
+        forms: (0..=0xFF)
+            .map(|op| disasm::decode(variant, &[op, 0, 0]).unwrap())
+            .collect(),
+        symbols: BTreeMap::new(),
+        loc: 0,
+        last: false,
+        listing: Vec::new(),
+    };
+    asm.pass(src)?;
+    asm.last = true;
+    asm.pass(src)?;
+    let emitted = asm.listing.iter().filter(|l| !l.bytes.is_empty());
+    let start = emitted.clone().map(|l| l.addr as usize).min().unwrap_or(0);
+    let end = emitted.clone().map(|l| l.addr as usize + l.bytes.len()).max().unwrap_or(0);
+    let mut image = vec![0; end - start];
+    for l in emitted {
+        let at = l.addr as usize - start;
+        image[at..at + l.bytes.len()].copy_from_slice(&l.bytes);
+    }
+    Ok(Assembly { origin: start as u16, image, symbols: asm.symbols, listing: asm.listing })
+}
+const DIRECTIVES: [&str; 6] = ["ORG", "DB", "DW", "DS", "EQU", "END"];
+struct Assembler {
+    /// The decoded form of every opcode, in opcode order so documented
+    /// encodings come before undocumented aliases.
+    forms: Vec<disasm::Instruction>,
+    symbols: BTreeMap<String, u16>,
+    /// Location counter; one past FFFFH is allowed at the very end.
+    loc: u32,
+    /// Second pass: every symbol is known and bytes are emitted.
+    last: bool,
+    listing: Vec<Line>,
+}
+/// A source line split into its fields.
+struct Fields<'s> {
+    label: Option<&'s str>,
+    op: Option<String>,
+    args: Vec<&'s str>,
+}
+impl Assembler {
+    fn pass(&mut self, src: &str) -> Result<(), AsmError> {
+        self.loc = 0;
+        for (i, text) in src.lines().enumerate() {
+            let number = i + 1;
+            let err = |kind| AsmError { line: number, kind };
+            let fields = self.split(text).map_err(err)?;
+            let addr = self.loc as u16;
+            let bytes = self.line(&fields).map_err(err)?;
+            if self.loc > 0x10000 {
+                return Err(err(ErrorKind::OutOfRange(self.loc as i32)));
+            }
+            if self.last {
+                self.listing.push(Line { number, addr, bytes, source: text.to_string() });
+            }
+            if fields.op.as_deref() == Some("END") {
+                break;
+            }
+        }
+        Ok(())
+    }
+    fn is_mnemonic(&self, word: &str) -> bool {
+        DIRECTIVES.contains(&word) || self.forms.iter().any(|i| i.mnemonic(Syntax::Intel) == word)
+    }
+    fn split<'s>(&self, text: &'s str) -> Result<Fields<'s>, ErrorKind> {
+        let code = strip_comment(text).trim_end();
+        let indented = code.starts_with([' ', '\t']);
+        let (first, rest) = word(code);
+        let mut fields = Fields { label: None, op: None, args: Vec::new() };
+        let rest = if let Some(label) = first.strip_suffix(':') {
+            fields.label = Some(label);
+            rest
+        } else if !first.is_empty()
+            && (!indented && !self.is_mnemonic(&first.to_ascii_uppercase())
+                || word(rest).0.eq_ignore_ascii_case("EQU"))
+        {
+            fields.label = Some(first);
+            rest
+        } else {
+            code
+        };
+        if let Some(label) = fields.label {
+            if !is_name(label) {
+                return Err(ErrorKind::Syntax(label.to_string()));
+            }
+        }
+        let (op, rest) = word(rest);
+        if !op.is_empty() {
+            fields.op = Some(op.to_ascii_uppercase());
+            fields.args = split_args(rest);
+        }
+        Ok(fields)
+    }
+    /// Runs one line's label and statement, returning the bytes it emits.
+    fn line(&mut self, fields: &Fields) -> Result<Vec<u8>, ErrorKind> {
+        let args = &fields.args;
+        let op = fields.op.as_deref();
+        if op == Some("EQU") {
+            let name = fields.label.ok_or(ErrorKind::MissingName)?;
+            let [arg] = args[..] else {
+                return Err(ErrorKind::InvalidOperands(args.join(",")));
+            };
+            let value = self.eval(arg, true)?;
+            self.define(name, word_range(value)?)?;
+            return Ok(Vec::new());
+        }
+        if let Some(label) = fields.label {
+            self.define(label, self.loc as u16)?;
+        }
+        let mut bytes = Vec::new();
+        match op {
+            None | Some("END") => {}
+            Some("ORG") | Some("DS") => {
+                let [arg] = args[..] else {
+                    return Err(ErrorKind::InvalidOperands(args.join(",")));
+                };
+                // Both are needed to place the following lines in the first pass.
+                let value = word_range(self.eval(arg, true)?)? as u32;
+                match op {
+                    Some("ORG") => self.loc = value,
+                    _ => self.loc += value,
+                }
+                return Ok(bytes);
+            }
+            Some("DB") => {
+                for arg in args {
+                    match string(arg) {
+                        Some(s) => bytes.extend(s),
+                        None => bytes.push(byte_range(self.eval(arg, self.last)?)?),
+                    }
+                }
+            }
+            Some("DW") => {
+                for arg in args {
+                    bytes.extend(word_range(self.eval(arg, self.last)?)?.to_le_bytes());
+                }
+            }
+            Some(mnemonic) => bytes = self.instruction(mnemonic, args)?,
+        }
+        self.loc += bytes.len() as u32;
+        Ok(bytes)
+    }
+    fn instruction(&self, mnemonic: &str, args: &[&str]) -> Result<Vec<u8>, ErrorKind> {
+        let mut known = false;
+        for form in &self.forms {
+            if form.mnemonic(Syntax::Intel) != mnemonic {
+                continue;
+            }
+            known = true;
+            if let Some(bytes) = self.fit(form, args)? {
+                return Ok(bytes);
+            }
+        }
+        Err(match known {
+            true => ErrorKind::InvalidOperands(args.join(",")),
+            false => ErrorKind::UnknownMnemonic(mnemonic.to_string()),
+        })
+    }
+    /// Encodes `args` as `form`, or `None` if the registers do not match it.
+    fn fit(&self, form: &disasm::Instruction, args: &[&str]) -> Result<Option<Vec<u8>>, ErrorKind> {
+        let template = form.operands(Syntax::Intel);
+        let tokens: Vec<&str> = match template {
+            "" => Vec::new(),
+            t => t.split(',').collect(),
+        };
+        if tokens.len() != args.len() {
+            return Ok(None);
+        }
+        let mut bytes = vec![form.opcode];
+        for (token, arg) in tokens.iter().zip(args) {
+            let token = token.trim_start_matches('{').trim_end_matches('}');
+            match token {
+                "b" => bytes.push(byte_range(self.eval(arg, self.last)?)?),
+                "w" => bytes.extend(word_range(self.eval(arg, self.last)?)?.to_le_bytes()),
+                "n" => {
+                    if self.eval(arg, true)? != (form.opcode >> 3 & 7) as i32 {
+                        return Ok(None);
+                    }
+                }
+                _ => match disasm::name(token, form.opcode, Syntax::Intel) {
+                    Some(name) if arg.eq_ignore_ascii_case(name) => {}
+                    _ => return Ok(None),
+                },
+            }
+        }
+        Ok(Some(bytes))
+    }
+    fn define(&mut self, name: &str, value: u16) -> Result<(), ErrorKind> {
+        let name = name.to_ascii_uppercase();
+        if self.last {
+            return Ok(());
+        }
+        if self.symbols.insert(name.clone(), value).is_some() {
+            return Err(ErrorKind::DuplicateSymbol(name));
+        }
+        Ok(())
+    }
+    /// Evaluates `text`; unless `strict`, undefined symbols read as 0 so the
+    /// first pass can size lines that refer forward.
+    fn eval(&self, text: &str, strict: bool) -> Result<i32, ErrorKind> {
+        let mut e = Eval { s: text.as_bytes(), pos: 0, asm: self, strict };
+        let value = e.binary(0)?;
+        e.skip_ws();
+        match e.pos == e.s.len() {
+            true => Ok(value),
+            false => Err(ErrorKind::Syntax(text[e.pos..].to_string())),
+        }
+    }
+}
+fn byte_range(v: i32) -> Result<u8, ErrorKind> {
+    match v {
+        -0x80..=0xFF => Ok(v as u8),
+        _ => Err(ErrorKind::OutOfRange(v)),
+    }
+}
+fn word_range(v: i32) -> Result<u16, ErrorKind> {
+    match v {
+        -0x8000..=0xFFFF => Ok(v as u16),
+        _ => Err(ErrorKind::OutOfRange(v)),
+    }
+}
+/// Splits off the first whitespace-delimited word.
+fn word(s: &str) -> (&str, &str) {
+    let s = s.trim_start();
+    let end = s.find(char::is_whitespace).unwrap_or(s.len());
+    (&s[..end], &s[end..])
+}
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '?' || c == '@')
+        && chars.all(is_name_char)
+}
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '?' || c == '@'
+}
+/// Drops a `;` comment, leaving semicolons inside quotes alone.
+fn strip_comment(s: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (c, quote) {
+            ('\'' | '"', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (';', None) => return &s[..i],
+            _ => {}
+        }
+    }
+    s
+}
+/// Splits operands on commas outside quotes.
+fn split_args(s: &str) -> Vec<&str> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Vec::new();
+    }
+    let mut args = Vec::new();
+    let (mut quote, mut start) = (None, 0);
+    for (i, c) in s.char_indices() {
+        match (c, quote) {
+            ('\'' | '"', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (',', None) => {
+                args.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(s[start..].trim());
+    args
+}
+/// The bytes of a quoted `DB` string, with a doubled quote standing for one.
+/// Single characters are left to the expression parser, so `'A'+80H` works.
+fn string(s: &str) -> Option<Vec<u8>> {
+    let q = s.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+    let body = s.strip_prefix(q)?.strip_suffix(q)?;
+    let mut out = Vec::new();
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == q && chars.next_if_eq(&q).is_none() {
+            return None;
+        }
+        out.push(c as u8);
+    }
+    Some(out)
+}
+struct Eval<'a> {
+    s: &'a [u8],
+    pos: usize,
+    asm: &'a Assembler,
+    strict: bool,
+}
+/// Binary operators from lowest to highest precedence.
+const LEVELS: [&[(&str, &str)]; 6] = [
+    &[("|", "OR")],
+    &[("^", "XOR")],
+    &[("&", "AND")],
+    &[("<<", "SHL"), (">>", "SHR")],
+    &[("+", ""), ("-", "")],
+    &[("*", ""), ("/", ""), ("%", "MOD")],
+];
+impl Eval<'_> {
+    fn skip_ws(&mut self) {
+        while self.s.get(self.pos).is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+    fn rest(&self) -> &str {
+        core::str::from_utf8(&self.s[self.pos..]).unwrap_or("")
+    }
+    fn error(&self) -> ErrorKind {
+        ErrorKind::Syntax(match self.rest() {
+            "" => "end of expression".to_string(),
+            rest => rest.to_string(),
+        })
+    }
+    /// Consumes `sym` or, when it is followed by a non-name character, `keyword`.
+    fn eat(&mut self, sym: &str, keyword: &str) -> bool {
+        self.skip_ws();
+        let rest = self.rest();
+        let len = if !sym.is_empty() && rest.starts_with(sym) {
+            sym.len()
+        } else if !keyword.is_empty()
+            && rest.get(..keyword.len()).is_some_and(|w| w.eq_ignore_ascii_case(keyword))
+            && !rest[keyword.len()..].starts_with(is_name_char)
+        {
+            keyword.len()
+        } else {
+            return false;
+        };
+        self.pos += len;
+        true
+    }
+    fn binary(&mut self, level: usize) -> Result<i32, ErrorKind> {
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+        let mut lhs = self.binary(level + 1)?;
+        'outer: loop {
+            for (sym, keyword) in LEVELS[level] {
+                if self.eat(sym, keyword) {
+                    let rhs = self.binary(level + 1)?;
+                    lhs = match *sym {
+                        "|" => lhs | rhs,
+                        "^" => lhs ^ rhs,
+                        "&" => lhs & rhs,
+                        "<<" => lhs.wrapping_shl(rhs as u32),
+                        ">>" => lhs.wrapping_shr(rhs as u32),
+                        "+" => lhs.wrapping_add(rhs),
+                        "-" => lhs.wrapping_sub(rhs),
+                        "*" => lhs.wrapping_mul(rhs),
+                        _ if rhs == 0 => return Err(ErrorKind::Syntax("division by zero".to_string())),
+                        "/" => lhs.wrapping_div(rhs),
+                        _ => lhs.wrapping_rem(rhs),
+                    };
+                    continue 'outer;
+                }
+            }
+            return Ok(lhs);
+        }
+    }
+    fn unary(&mut self) -> Result<i32, ErrorKind> {
+        if self.eat("-", "") {
+            return Ok(self.unary()?.wrapping_neg());
+        }
+        if self.eat("+", "") {
+            return self.unary();
+        }
+        if self.eat("~", "NOT") {
+            return Ok(!self.unary()?);
+        }
+        if self.eat("", "HIGH") {
+            return Ok(self.unary()? >> 8 & 0xFF);
+        }
+        if self.eat("", "LOW") {
+            return Ok(self.unary()? & 0xFF);
+        }
+        if self.eat("(", "") {
+            let v = self.binary(0)?;
+            if !self.eat(")", "") {
+                return Err(self.error());
+            }
+            return Ok(v);
+        }
+        self.primary()
+    }
+    fn primary(&mut self) -> Result<i32, ErrorKind> {
+        self.skip_ws();
+        let start = self.pos;
+        match self.s.get(self.pos).copied() {
+            Some(b'$') => {
+                self.pos += 1;
+                Ok(self.asm.loc as i32)
+            }
+            Some(q @ (b'\'' | b'"')) => {
+                // A single character, or a doubled quote for the quote itself.
+                let lit = match self.s.get(self.pos + 1..self.pos + 4) {
+                    Some([a, b, c]) if *a == q && *b == q && *c == q => 4,
+                    _ => 3,
+                };
+                match self.s.get(self.pos..self.pos + lit) {
+                    Some(l) if l[lit - 1] == q && (lit == 4 || l[1] != q) => {
+                        self.pos += lit;
+                        Ok(l[1] as i32)
+                    }
+                    _ => Err(self.error()),
+                }
+            }
+            Some(c) if c.is_ascii_digit() => {
+                while self.s.get(self.pos).is_some_and(|c| c.is_ascii_alphanumeric()) {
+                    self.pos += 1;
+                }
+                let text = self.rest_from(start).to_ascii_uppercase();
+                number(&text).ok_or(ErrorKind::Syntax(text))
+            }
+            Some(c) if is_name_char(c as char) => {
+                while self.s.get(self.pos).is_some_and(|c| is_name_char(*c as char)) {
+                    self.pos += 1;
+                }
+                let name = self.rest_from(start).to_ascii_uppercase();
+                match self.asm.symbols.get(&name) {
+                    Some(v) => Ok(*v as i32),
+                    None if self.strict => Err(ErrorKind::UndefinedSymbol(name)),
+                    None => Ok(0),
+                }
+            }
+            _ => Err(self.error()),
+        }
+    }
+    fn rest_from(&self, start: usize) -> &str {
+        core::str::from_utf8(&self.s[start..self.pos]).unwrap_or("")
+    }
+}
+/// `1234`, `1234D`, `0FFH`, `0xFF`, `1010B`, `17O` or `17Q`.
+fn number(text: &str) -> Option<i32> {
+    let (digits, radix) = if let Some(hex) = text.strip_prefix("0X") {
+        (hex, 16)
+    } else {
+        match text.as_bytes()[text.len() - 1] {
+            b'H' => (&text[..text.len() - 1], 16),
+            b'B' => (&text[..text.len() - 1], 2),
+            b'O' | b'Q' => (&text[..text.len() - 1], 8),
+            b'D' => (&text[..text.len() - 1], 10),
+            _ => (text, 10),
+        }
+    };
+    u16::from_str_radix(digits, radix).ok().map(i32::from)
+}
@@ -0,0 +1,96 @@
+//! Runtime configuration of a new core, replacing the `start_regs` feature.
+use super::regs::Registers;
+use super::{Variant, CPU};
+
+/// What the registers hold before the program sets them. The real parts come
+/// up with arbitrary contents, so software that reads a register before
+/// writing it can behave differently under each setting.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum PowerOn {
+    #[default]
+    Zeroed,
+    /// Every register byte, SP included, set to this value.
+    Filled(u8),
+    /// Pseudo-random contents from a seed, repeatable across runs.
+    Random(u64),
+}
+/// Builds a `CPU`. Power-on contents are applied first, then `registers`,
+/// then `pc` and `sp`.
+///
+/// ```
+/// use i8080_core::cpu::builder::{CpuBuilder, PowerOn};
+/// let cpu = CpuBuilder::new().power_on(PowerOn::Filled(0xFF)).pc(0x0100).build();
+/// assert_eq!((cpu.regs.pc, cpu.regs.b), (0x0100, 0xFF));
+/// ```
+#[derive(Debug, Copy, Clone, Default)]
+pub struct CpuBuilder {
+    variant: Variant,
+    power_on: PowerOn,
+    registers: Option<Registers>,
+    pc: Option<u16>,
+    sp: Option<u16>,
+}
+impl CpuBuilder {
+    pub fn new() -> CpuBuilder {
+        CpuBuilder::default()
+    }
+    pub fn variant(mut self, variant: Variant) -> CpuBuilder {
+        self.variant = variant;
+        self
+    }
+    pub fn power_on(mut self, power_on: PowerOn) -> CpuBuilder {
+        self.power_on = power_on;
+        self
+    }
+    /// Starts from this complete register set instead of the power-on contents.
+    pub fn registers(mut self, registers: Registers) -> CpuBuilder {
+        self.registers = Some(registers);
+        self
+    }
+    pub fn pc(mut self, pc: u16) -> CpuBuilder {
+        self.pc = Some(pc);
+        self
+    }
+    pub fn sp(mut self, sp: u16) -> CpuBuilder {
+        self.sp = Some(sp);
+        self
+    }
+    pub fn build(self) -> CPU {
+        let mut cpu = CPU::with_variant(self.variant);
+        let mut bytes = [0u8; 10];
+        match self.power_on {
+            PowerOn::Zeroed => {}
+            PowerOn::Filled(v) => bytes = [v; 10],
+            PowerOn::Random(seed) => {
+                // SplitMix64.
+                let mut x = seed;
+                for b in &mut bytes {
+                    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                    let mut z = x;
+                    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                    *b = (z ^ (z >> 31)) as u8;
+                }
+            }
+        }
+        let r = &mut cpu.regs;
+        [r.a, r.b, r.c, r.d, r.e, r.h, r.l] = [bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6]];
+        r.sp = u16::from_le_bytes([bytes[8], bytes[9]]);
+        cpu.set_psw(bytes[7]);
+        if let Some(registers) = self.registers {
+            cpu.regs = registers;
+        }
+        if let Some(pc) = self.pc {
+            cpu.regs.pc = pc;
+        }
+        if let Some(sp) = self.sp {
+            cpu.regs.sp = sp;
+        }
+        cpu
+    }
+}
+impl CPU {
+    pub fn builder() -> CpuBuilder {
+        CpuBuilder::new()
+    }
+}
@@ -0,0 +1,124 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::ops::IndexMut;
+/// Memory as seen by the CPU. Reads and writes are separate calls so that an
+/// implementation can map I/O into the address space, protect ROM or switch banks.
+pub trait Bus {
+    fn read(&mut self, addr: u16) -> u8;
+    fn write(&mut self, addr: u16, val: u8);
+    /// Reads without the side effects a CPU read may have, such as popping a
+    /// FIFO or clearing a status flag, for debuggers and journals. Defaults to
+    /// a plain read; memory-mapped devices should override it.
+    fn peek(&mut self, addr: u16) -> u8 {
+        self.read(addr)
+    }
+    /// Opcode fetch (M1 cycle). Defaults to a plain read.
+    fn fetch(&mut self, addr: u16) -> u8 {
+        self.read(addr)
+    }
+    /// Samples HOLD before every machine cycle, `t_state` being where the
+    /// cycle would start. While it returns `true` the bus is released (HLDA)
+    /// and the core idles one state per call, so a DMA device can move its
+    /// data here; the states are counted like wait states. Never holds by
+    /// default.
+    fn hold(&mut self, _t_state: u32) -> bool {
+        false
+    }
+    /// Called at the start of every machine cycle, before its access, with the
+    /// T-state offset into the instruction and the status word. I/O, halt and
+    /// interrupt acknowledge cycles are reported here too. Returns how many wait
+    /// states READY holds the cycle for; they are added to the instruction's
+    /// cycle count. Does nothing and adds no waits by default.
+    fn cycle(&mut self, _cycle: MachineCycle) -> u8 {
+        0
+    }
+}
+/// The status word the 8080 puts on the data bus at T1 of each machine cycle.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Status(pub u8);
+impl Status {
+    pub const INTA: u8 = 0x01;
+    /// Active low: set for reads, clear for write and output cycles.
+    pub const WO: u8 = 0x02;
+    pub const STACK: u8 = 0x04;
+    pub const HLTA: u8 = 0x08;
+    pub const OUT: u8 = 0x10;
+    pub const M1: u8 = 0x20;
+    pub const INP: u8 = 0x40;
+    pub const MEMR: u8 = 0x80;
+
+    pub const FETCH: Status = Status(Self::MEMR | Self::M1 | Self::WO);
+    pub const MEMORY_READ: Status = Status(Self::MEMR | Self::WO);
+    pub const MEMORY_WRITE: Status = Status(0);
+    pub const STACK_READ: Status = Status(Self::MEMR | Self::STACK | Self::WO);
+    pub const STACK_WRITE: Status = Status(Self::STACK);
+    pub const INPUT_READ: Status = Status(Self::INP | Self::WO);
+    pub const OUTPUT_WRITE: Status = Status(Self::OUT);
+    pub const INTERRUPT_ACK: Status = Status(Self::INTA | Self::M1 | Self::WO);
+    pub const HALT_ACK: Status = Status(Self::HLTA | Self::MEMR | Self::WO);
+
+    pub fn is_memr(self) -> bool {
+        self.0 & Self::MEMR != 0
+    }
+    pub fn is_memw(self) -> bool {
+        self.0 & (Self::WO | Self::OUT) == 0
+    }
+    pub fn is_ior(self) -> bool {
+        self.0 & Self::INP != 0
+    }
+    pub fn is_iow(self) -> bool {
+        self.0 & Self::OUT != 0
+    }
+    pub fn is_inta(self) -> bool {
+        self.0 & Self::INTA != 0
+    }
+    pub fn is_stack(self) -> bool {
+        self.0 & Self::STACK != 0
+    }
+    pub fn is_hlta(self) -> bool {
+        self.0 & Self::HLTA != 0
+    }
+    pub fn is_m1(self) -> bool {
+        self.0 & Self::M1 != 0
+    }
+}
+/// One machine cycle as reported to `Bus::cycle`. For I/O cycles `addr`
+/// carries the port number on both halves, as the 8080 drives it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MachineCycle {
+    /// T-state at which the cycle starts, counted from T1 of the opcode fetch
+    /// and including any wait states of earlier cycles.
+    pub t_state: u32,
+    pub status: Status,
+    pub addr: u16,
+}
+impl Bus for [u8; 0x10000] {
+    fn read(&mut self, addr: u16) -> u8 {
+        self[addr as usize]
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        self[addr as usize] = val;
+    }
+}
+/// Addresses past the end of the vector read as `0xFF` and ignore writes.
+#[cfg(feature = "alloc")]
+impl Bus for Vec<u8> {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.get(addr as usize).copied().unwrap_or(0xFF)
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        if let Some(b) = self.get_mut(addr as usize) {
+            *b = val;
+        }
+    }
+}
+/// Adapter for memory types that implement `IndexMut<u16, Output = u8>`.
+pub struct Indexed<T>(pub T);
+impl<T: IndexMut<u16, Output = u8>> Bus for Indexed<T> {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.0[addr]
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        self.0[addr] = val;
+    }
+}
@@ -0,0 +1,252 @@
+//! Instruction decoding and formatting, built from the same bit patterns as
+//! the dispatch table so the two cannot disagree.
+use core::fmt;
+use core::sync::atomic::{AtomicU16, Ordering};
+use super::bus::Bus;
+use super::io::IoBus;
+use super::{place, Dispatch, Opcode, Variant, CPU};
+
+/// Mnemonic flavour used when formatting an `Instruction`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Syntax {
+    /// `MOV A,M`, `LXI H,1234H`
+    #[default]
+    Intel,
+    /// `LD A,(HL)`, `LD HL,1234H`
+    Zilog,
+}
+/// One decoded instruction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub opcode: u8,
+    /// The immediate byte or word following the opcode, if there is one.
+    pub operand: Option<u16>,
+    /// Length in bytes, opcode included.
+    pub len: u8,
+    /// States the instruction takes; for conditionals, when the branch is taken.
+    pub cycles: u8,
+    /// States a conditional jump, call or return takes when it is not taken.
+    pub cycles_not_taken: Option<u8>,
+    templates: (&'static str, &'static str),
+}
+type Templates = (&'static str, &'static str);
+const fn templates(mut lut: [Templates; 0x100], table: &[Opcode<dyn Bus, dyn IoBus>]) -> [Templates; 0x100] {
+    let mut i = 0;
+    while i < table.len() {
+        place(&mut lut, table[i].0, (table[i].1, table[i].2));
+        i += 1;
+    }
+    lut
+}
+const TEMPLATES: [Templates; 0x100] = templates([("DB {op}", "DB {op}"); 0x100], &Dispatch::<dyn Bus, dyn IoBus>::INDEX);
+#[cfg(feature = "i8085")]
+const TEMPLATES_8085: [Templates; 0x100] = templates(TEMPLATES, &Dispatch::<dyn Bus, dyn IoBus>::INDEX_8085);
+
+fn lookup(variant: Variant, opcode: u8) -> Templates {
+    match variant {
+        Variant::I8080 => TEMPLATES[opcode as usize],
+        #[cfg(feature = "i8085")]
+        Variant::I8085 => TEMPLATES_8085[opcode as usize],
+    }
+}
+/// Length in bytes of `opcode` on `variant`.
+pub fn length(variant: Variant, opcode: u8) -> u8 {
+    let intel = lookup(variant, opcode).0;
+    if intel.contains("{w}") {
+        3
+    } else if intel.contains("{b}") {
+        2
+    } else {
+        1
+    }
+}
+/// Decodes the instruction at the start of `bytes`. Returns `None` if the
+/// slice is shorter than the instruction.
+pub fn decode(variant: Variant, bytes: &[u8]) -> Option<Instruction> {
+    let opcode = *bytes.first()?;
+    let len = length(variant, opcode) as usize;
+    let bytes = bytes.get(..len)?;
+    let mut raw = [opcode, 0, 0];
+    raw[..len].copy_from_slice(bytes);
+    let operand = match len {
+        3 => Some((raw[2] as u16) << 8 | raw[1] as u16),
+        2 => Some(raw[1] as u16),
+        _ => None,
+    };
+    let (cycles, cycles_not_taken) = timing(variant, opcode);
+    Some(Instruction {
+        opcode,
+        operand,
+        len: len as u8,
+        cycles,
+        cycles_not_taken,
+        templates: lookup(variant, opcode),
+    })
+}
+/// Decodes the instruction at `addr`, taking its bytes with `Bus::peek`.
+pub fn decode_at<M: Bus + ?Sized>(variant: Variant, mem: &mut M, addr: u16) -> Instruction {
+    let opcode = mem.peek(addr);
+    let mut raw = [opcode, 0, 0];
+    for i in 1..length(variant, opcode) {
+        raw[i as usize] = mem.peek(addr.wrapping_add(i as u16));
+    }
+    // `raw` always holds enough bytes.
+    decode(variant, &raw).unwrap()
+}
+/// `measure` results by opcode, filled in on first use: states taken in the
+/// low byte, states not taken in the high byte (0 if the same), 0 until then.
+static TIMING: [AtomicU16; 0x100] = [const { AtomicU16::new(0) }; 0x100];
+#[cfg(feature = "i8085")]
+static TIMING_8085: [AtomicU16; 0x100] = [const { AtomicU16::new(0) }; 0x100];
+
+fn timing(variant: Variant, opcode: u8) -> (u8, Option<u8>) {
+    let slot = match variant {
+        Variant::I8080 => &TIMING[opcode as usize],
+        #[cfg(feature = "i8085")]
+        Variant::I8085 => &TIMING_8085[opcode as usize],
+    };
+    let mut packed = slot.load(Ordering::Relaxed);
+    if packed == 0 {
+        let (cycles, not_taken) = measure(variant, opcode);
+        packed = u16::from_le_bytes([cycles, not_taken.unwrap_or(0)]);
+        slot.store(packed, Ordering::Relaxed);
+    }
+    let [cycles, not_taken] = packed.to_le_bytes();
+    (cycles, (not_taken != 0).then_some(not_taken))
+}
+/// Runs the instruction on a scratch core, once with every flag clear and
+/// once with every flag set, so each condition is both taken and not.
+fn measure(variant: Variant, opcode: u8) -> (u8, Option<u8>) {
+    let raw = [opcode, 0, 0];
+    struct Scratch([u8; 3]);
+    impl Bus for Scratch {
+        fn read(&mut self, addr: u16) -> u8 {
+            self.0.get(addr as usize).copied().unwrap_or(0)
+        }
+        fn write(&mut self, _addr: u16, _val: u8) {}
+    }
+    impl IoBus for Scratch {
+        fn read_port(&mut self, _port: u8) -> u8 {
+            0
+        }
+        fn write_port(&mut self, _port: u8, _value: u8) {}
+    }
+    let run = |flags: u8| {
+        let mut cpu = CPU::with_variant(variant);
+        cpu.set_psw(flags);
+        cpu.instruction = raw[0];
+        cpu.dispatch(&mut Scratch(raw), &mut Scratch(raw))
+    };
+    let (clear, set) = (run(0x00), run(0xFF));
+    match clear == set {
+        true => (clear, None),
+        false => (clear.max(set), Some(clear.min(set))),
+    }
+}
+const REGS: [&str; 8] = ["B", "C", "D", "E", "H", "L", "M", "A"];
+const REGS_Z80: [&str; 8] = ["B", "C", "D", "E", "H", "L", "(HL)", "A"];
+const PAIRS: [&str; 4] = ["B", "D", "H", "SP"];
+const PAIRS_Z80: [&str; 4] = ["BC", "DE", "HL", "SP"];
+const CONDS: [&str; 8] = ["NZ", "Z", "NC", "C", "PO", "PE", "P", "M"];
+const JUMPS: [&str; 8] = ["JNZ", "JZ", "JNC", "JC", "JPO", "JPE", "JP", "JM"];
+const CALLS: [&str; 8] = ["CNZ", "CZ", "CNC", "CC", "CPO", "CPE", "CP", "CM"];
+const RETURNS: [&str; 8] = ["RNZ", "RZ", "RNC", "RC", "RPO", "RPE", "RP", "RM"];
+/// The register, pair or condition a template token names in `op`.
+pub(crate) fn name(token: &str, op: u8, syntax: Syntax) -> Option<&'static str> {
+    let zilog = syntax == Syntax::Zilog;
+    let pair = (op >> 4 & 3) as usize;
+    Some(match token {
+        "d" if zilog => REGS_Z80[(op >> 3 & 7) as usize],
+        "d" => REGS[(op >> 3 & 7) as usize],
+        "s" if zilog => REGS_Z80[(op & 7) as usize],
+        "s" => REGS[(op & 7) as usize],
+        // PUSH and POP name the accumulator and flags instead of SP.
+        "rp" if pair == 3 && op & 0xC0 == 0xC0 => if zilog { "AF" } else { "PSW" },
+        "rp" if zilog => PAIRS_Z80[pair],
+        "rp" => PAIRS[pair],
+        "c" => CONDS[(op >> 3 & 7) as usize],
+        "k" => if op & 0x20 != 0 { "K" } else { "NK" },
+        _ => return None,
+    })
+}
+impl Instruction {
+    /// The mnemonic alone, e.g. `JNZ` or, in Zilog syntax, `JP`.
+    pub fn mnemonic(&self, syntax: Syntax) -> &'static str {
+        let template = match syntax {
+            Syntax::Intel => self.templates.0,
+            Syntax::Zilog => self.templates.1,
+        };
+        let name = template.split(' ').next().unwrap_or(template);
+        let cc = (self.opcode >> 3 & 7) as usize;
+        match name {
+            "J{c}" => JUMPS[cc],
+            "C{c}" => CALLS[cc],
+            "R{c}" => RETURNS[cc],
+            "J{k}" if self.opcode & 0x20 != 0 => "JK",
+            "J{k}" => "JNK",
+            _ => name,
+        }
+    }
+    /// The operand template following the mnemonic, e.g. `{d},{s}`.
+    pub(crate) fn operands(&self, syntax: Syntax) -> &'static str {
+        let template = match syntax {
+            Syntax::Intel => self.templates.0,
+            Syntax::Zilog => self.templates.1,
+        };
+        template.split_once(' ').map_or("", |(_, rest)| rest)
+    }
+    /// Formats the instruction in the given syntax; `Display` uses Intel.
+    pub fn display(&self, syntax: Syntax) -> Formatted<'_> {
+        Formatted { instr: self, syntax }
+    }
+}
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display(Syntax::Intel).fmt(f)
+    }
+}
+/// An `Instruction` paired with the syntax to print it in.
+pub struct Formatted<'a> {
+    instr: &'a Instruction,
+    syntax: Syntax,
+}
+/// Intel style hex: a trailing `H`, and a leading `0` if the first digit is a letter.
+fn hex(f: &mut fmt::Formatter<'_>, val: u16, width: usize) -> fmt::Result {
+    let lead = val >> (4 * (width - 1)) >= 0xA;
+    if lead {
+        f.write_str("0")?;
+    }
+    write!(f, "{:0width$X}H", val, width = width)
+}
+impl fmt::Display for Formatted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let i = self.instr;
+        let zilog = self.syntax == Syntax::Zilog;
+        let mut rest = i.operands(self.syntax);
+        f.write_str(i.mnemonic(self.syntax))?;
+        if !rest.is_empty() {
+            f.write_str(" ")?;
+        }
+        let op = i.opcode;
+        let operand = i.operand.unwrap_or(0);
+        while !rest.is_empty() {
+            let Some(start) = rest.find('{') else {
+                return f.write_str(rest);
+            };
+            f.write_str(&rest[..start])?;
+            let end = start + rest[start..].find('}').unwrap_or(0);
+            match &rest[start + 1..end] {
+                "n" if zilog => hex(f, (op & 0x38) as u16, 2)?,
+                "n" => write!(f, "{}", op >> 3 & 7)?,
+                "b" => hex(f, operand, 2)?,
+                "w" => hex(f, operand, 4)?,
+                token => match name(token, op, self.syntax) {
+                    Some(name) => f.write_str(name)?,
+                    None => hex(f, op as u16, 2)?,
+                },
+            }
+            rest = &rest[end + 1..];
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use core::fmt;
+/// Reasons `CPU::try_step` stops instead of completing an instruction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CpuError {
+    /// The opcode at `pc` has no handler; PC is left pointing at it.
+    UndefinedOpcode { pc: u16, opcode: u8 },
+    /// The core is halted and no interrupt was accepted; the idle cycles were counted.
+    Halted { pc: u16 },
+    /// HOLD is asserted and the bus is granted (HLDA); one idle state was counted.
+    Held { pc: u16 },
+    /// A breakpoint is set at `pc`. The instruction has not run yet; the next
+    /// `try_step` executes it.
+    Breakpoint { pc: u16 },
+}
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CpuError::UndefinedOpcode { pc, opcode } => {
+                write!(f, "undefined opcode {:02X} at {:04X}", opcode, pc)
+            }
+            CpuError::Halted { pc } => write!(f, "halted at {:04X}", pc),
+            CpuError::Held { pc } => write!(f, "bus held at {:04X}", pc),
+            CpuError::Breakpoint { pc } => write!(f, "breakpoint at {:04X}", pc),
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for CpuError {}
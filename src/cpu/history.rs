@@ -0,0 +1,140 @@
+//! Reverse stepping. `CPU::step_recorded` runs one step and keeps the core's
+//! state from before it, plus the old contents of every byte it wrote, in a
+//! `History`; `History::step_back` and `History::rewind_to_cycle` put both
+//! back. Oldest steps are dropped once the history outgrows its budget.
+use alloc::collections::VecDeque;
+use core::mem::size_of;
+use super::bus::{Bus, MachineCycle};
+use super::io::IoBus;
+use super::CPU;
+
+/// One `next` call: the core before it and how many entries it added to `writes`.
+#[derive(Debug, Copy, Clone)]
+struct Step {
+    cpu: CPU,
+    writes: usize,
+}
+/// A bounded log of recorded steps, newest last.
+#[derive(Debug, Clone)]
+pub struct History {
+    steps: VecDeque<Step>,
+    /// Address and previous value of every byte written, oldest first.
+    writes: VecDeque<(u16, u8)>,
+    budget: usize,
+}
+impl History {
+    /// A history holding at most about `budget` bytes of steps and memory deltas.
+    pub fn new(budget: usize) -> History {
+        History { steps: VecDeque::new(), writes: VecDeque::new(), budget }
+    }
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+    /// Shrinking the budget drops the oldest steps straight away.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.trim();
+    }
+    /// Bytes taken by the recorded steps.
+    pub fn bytes_used(&self) -> usize {
+        self.steps.len() * size_of::<Step>() + self.writes.len() * size_of::<(u16, u8)>()
+    }
+    /// How many steps can be undone.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.steps.clear();
+        self.writes.clear();
+    }
+    /// The cycle counter before the oldest step still held.
+    pub fn oldest_cycle(&self) -> Option<u64> {
+        self.steps.front().map(|s| s.cpu.cycles)
+    }
+    /// Undoes up to `n` steps, restoring the core and the memory they wrote.
+    /// Breakpoints set since are kept. Returns how many steps were undone.
+    pub fn step_back<M: Bus + ?Sized>(&mut self, cpu: &mut CPU, mem: &mut M, n: usize) -> usize {
+        for done in 0..n {
+            if !self.undo(cpu, mem) {
+                return done;
+            }
+        }
+        n
+    }
+    /// Undoes steps until the cycle counter is at or below `cycle`, landing on
+    /// the start of the instruction that was running then. Returns false if
+    /// the history ran out first; the core is then at the oldest step held.
+    pub fn rewind_to_cycle<M: Bus + ?Sized>(&mut self, cpu: &mut CPU, mem: &mut M, cycle: u64) -> bool {
+        while cpu.cycles > cycle {
+            if !self.undo(cpu, mem) {
+                return false;
+            }
+        }
+        true
+    }
+    fn undo<M: Bus + ?Sized>(&mut self, cpu: &mut CPU, mem: &mut M) -> bool {
+        let Some(step) = self.steps.pop_back() else {
+            return false;
+        };
+        // Newest first, so a byte written twice ends up with its oldest value.
+        for _ in 0..step.writes {
+            if let Some((addr, val)) = self.writes.pop_back() {
+                mem.write(addr, val);
+            }
+        }
+        let breakpoints = cpu.breakpoints;
+        *cpu = step.cpu;
+        cpu.breakpoints = breakpoints;
+        true
+    }
+    fn trim(&mut self) {
+        while self.bytes_used() > self.budget {
+            let Some(step) = self.steps.pop_front() else {
+                return;
+            };
+            self.writes.drain(..step.writes);
+        }
+    }
+}
+/// Memory wrapper saving what each write overwrites. The old byte is taken
+/// with `Bus::peek`, so journaling does not disturb memory-mapped devices.
+struct Journal<'a, M: ?Sized> {
+    mem: &'a mut M,
+    writes: &'a mut VecDeque<(u16, u8)>,
+}
+impl<M: Bus + ?Sized> Bus for Journal<'_, M> {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.mem.read(addr)
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        self.writes.push_back((addr, self.mem.peek(addr)));
+        self.mem.write(addr, val);
+    }
+    fn peek(&mut self, addr: u16) -> u8 {
+        self.mem.peek(addr)
+    }
+    fn fetch(&mut self, addr: u16) -> u8 {
+        self.mem.fetch(addr)
+    }
+    fn hold(&mut self, t_state: u32) -> bool {
+        self.mem.hold(t_state)
+    }
+    fn cycle(&mut self, cycle: MachineCycle) -> u8 {
+        self.mem.cycle(cycle)
+    }
+}
+impl CPU {
+    /// `next`, recording the step in `history` so it can be undone.
+    pub fn step_recorded<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, io:&mut I, history:&mut History) ->u32{
+        let before = *self;
+        let start = history.writes.len();
+        let cycles = self.next(&mut Journal { mem, writes: &mut history.writes }, io);
+        let writes = history.writes.len() - start;
+        history.steps.push_back(Step { cpu: before, writes });
+        history.trim();
+        cycles
+    }
+}
@@ -0,0 +1,266 @@
+//! Intel 8085 extensions: RIM/SIM, the TRAP and RST 5.5/6.5/7.5 interrupt
+//! inputs, the serial I/O pins and the undocumented 8085 instructions.
+use super::bus::Bus;
+use super::io::IoBus;
+use super::{Variant, CPU};
+
+/// Interrupt inputs, mask register and serial pins of the 8085. Power-on is
+/// the same as RESET IN, with RST 5.5, 6.5 and 7.5 masked.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Interrupts8085 {
+    mask: u8,
+    rst55: bool,
+    rst65: bool,
+    rst75: bool,
+    trap: bool,
+    trap_ie: Option<bool>,
+    sid: bool,
+    sod: bool,
+}
+impl Default for Interrupts8085 {
+    fn default() -> Interrupts8085 {
+        Interrupts8085 {
+            mask: 0x07,
+            rst55: false,
+            rst65: false,
+            rst75: false,
+            trap: false,
+            trap_ie: None,
+            sid: false,
+            sod: false,
+        }
+    }
+}
+impl Interrupts8085 {
+    /// RESET IN masks RST 5.5, 6.5 and 7.5, drops a latched RST 7.5 and clears SOD.
+    pub(super) fn reset(&mut self) {
+        self.mask = 0x07;
+        self.rst75 = false;
+        self.trap_ie = None;
+        self.sod = false;
+    }
+    /// Packs the state for `snapshot`: mask, input and pin bits, and the INTE
+    /// saved by TRAP (0 none, 1 clear, 2 set).
+    #[cfg(feature = "alloc")]
+    pub(super) fn to_bytes(self) -> [u8; 3] {
+        let pins = [self.rst55, self.rst65, self.rst75, self.trap, self.sid, self.sod]
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (i, b)| acc | (*b as u8) << i);
+        [self.mask, pins, self.trap_ie.map_or(0, |ie| 1 + ie as u8)]
+    }
+    #[cfg(feature = "alloc")]
+    pub(super) fn from_bytes(b: [u8; 3]) -> Interrupts8085 {
+        let pin = |i: u8| b[1] >> i & 1 != 0;
+        Interrupts8085 {
+            mask: b[0],
+            rst55: pin(0),
+            rst65: pin(1),
+            rst75: pin(2),
+            trap: pin(3),
+            sid: pin(4),
+            sod: pin(5),
+            trap_ie: b[2].checked_sub(1).map(|ie| ie != 0),
+        }
+    }
+}
+impl CPU {
+    /// Pulses TRAP. It is serviced at the next instruction boundary whatever the
+    /// state of INTE or the mask register.
+    pub fn trap(&mut self) {
+        self.i8085.trap = true;
+    }
+    /// Drives the level-sensitive RST 5.5 input.
+    pub fn set_rst55(&mut self, level: bool) {
+        self.i8085.rst55 = level;
+    }
+    /// Drives the level-sensitive RST 6.5 input.
+    pub fn set_rst65(&mut self, level: bool) {
+        self.i8085.rst65 = level;
+    }
+    /// Pulses the edge-triggered RST 7.5 input; the request stays latched until
+    /// serviced or cleared by SIM.
+    pub fn rst75(&mut self) {
+        self.i8085.rst75 = true;
+    }
+    pub fn set_sid(&mut self, level: bool) {
+        self.i8085.sid = level;
+    }
+    pub fn sod(&self) -> bool {
+        self.i8085.sod
+    }
+    /// V, two's complement overflow: PSW bit 1.
+    pub fn v_flag(&self) -> bool {
+        self.kv & 0x02 != 0
+    }
+    /// K (X5): PSW bit 5, set by INX/DCX wrap-around and to S xor V by arithmetic.
+    pub fn k_flag(&self) -> bool {
+        self.kv & 0x20 != 0
+    }
+    fn set_v(&mut self, v: bool) {
+        self.kv = self.kv & !0x02 | (v as u8) << 1;
+    }
+    fn set_k(&mut self, k: bool) {
+        self.kv = self.kv & !0x20 | (k as u8) << 5;
+    }
+    /// The M7.5, M6.5 and M5.5 mask bits as last set by SIM.
+    pub fn interrupt_mask(&self) -> u8 {
+        self.i8085.mask
+    }
+    pub(super) fn vectored_interrupt(&mut self, accept: bool) -> Option<u16> {
+        if self.variant != Variant::I8085 {
+            return None;
+        }
+        let s = &mut self.i8085;
+        if s.trap {
+            s.trap = false;
+            s.trap_ie = Some(self.interrupt_enabled);
+            return Some(0x24);
+        }
+        if !accept {
+            return None;
+        }
+        if s.rst75 && s.mask & 0x04 == 0 {
+            s.rst75 = false;
+            return Some(0x3C);
+        }
+        if s.rst65 && s.mask & 0x02 == 0 {
+            return Some(0x34);
+        }
+        if s.rst55 && s.mask & 0x01 == 0 {
+            return Some(0x2C);
+        }
+        None
+    }
+    /// Drives the input that jumps to `vector`, for `step_replaying`.
+    #[cfg(feature = "alloc")]
+    pub(super) fn drive_vector(&mut self, vector: u16, level: bool) {
+        let s = &mut self.i8085;
+        match vector {
+            0x24 => s.trap = level,
+            0x2C => s.rst55 = level,
+            0x34 => s.rst65 = level,
+            0x3C => s.rst75 = level,
+            _ => {}
+        }
+    }
+    /// Sets V and K (V xor sign) after an arithmetic result `r`.
+    pub(super) fn overflow(&mut self, v: bool, r: u8) {
+        if self.variant != Variant::I8085 {
+            return;
+        }
+        self.set_v(v);
+        self.set_k(v ^ (r & 0x80 == 0x80));
+    }
+    pub(super) fn carry_16(&mut self, k: bool) {
+        if self.variant == Variant::I8085 {
+            self.set_k(k);
+        }
+    }
+    pub(super) fn rim<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
+        let s = &mut self.i8085;
+        let ie = s.trap_ie.take().unwrap_or(self.interrupt_enabled);
+        self.regs.a = (s.sid as u8) << 7
+            | (s.rst75 as u8) << 6
+            | (s.rst65 as u8) << 5
+            | (s.rst55 as u8) << 4
+            | (ie as u8) << 3
+            | s.mask;
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        4
+    }
+    pub(super) fn sim<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
+        let a = self.regs.a;
+        let s = &mut self.i8085;
+        if a & 0x08 != 0 {
+            s.mask = a & 0x07;
+        }
+        if a & 0x10 != 0 {
+            s.rst75 = false;
+        }
+        if a & 0x40 != 0 {
+            s.sod = a & 0x80 != 0;
+        }
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        4
+    }
+    pub(super) fn dsub<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
+        let hl = self.regs.get_rp(0x20);
+        let bc = self.regs.get_rp(0x00);
+        let (r, c) = hl.overflowing_sub(bc);
+        let (h, b, rh) = ((hl >> 8) as u8, (bc >> 8) as u8, (r >> 8) as u8);
+        self.regs.set_flags(rh, c, (h ^ b ^ rh) & 0x10 == 0x10);
+        self.regs.f.set_zero(r == 0);
+        self.overflow((h ^ b) & (h ^ rh) & 0x80 == 0x80, rh);
+        self.regs.set_rp(r, 0x20);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        10
+    }
+    pub(super) fn arhl<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
+        let hl = self.regs.get_rp(0x20);
+        self.regs.f.set_carry(hl & 1 == 1);
+        self.regs.set_rp(((hl as i16) >> 1) as u16, 0x20);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        7
+    }
+    pub(super) fn rdel<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
+        let de = self.regs.get_rp(0x10);
+        let r = de << 1 | self.regs.f.get_carry() as u16;
+        self.regs.f.set_carry(de & 0x8000 == 0x8000);
+        self.set_v((de ^ r) & 0x8000 == 0x8000);
+        self.regs.set_rp(r, 0x10);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        10
+    }
+    pub(super) fn ldhi<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let db = self.operand(mem, 1);
+        let de = self.regs.get_rp(0x20).wrapping_add(db as u16);
+        self.regs.set_rp(de, 0x10);
+        self.regs.pc = self.regs.pc.wrapping_add(2);
+        10
+    }
+    pub(super) fn ldsi<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let db = self.operand(mem, 1);
+        let de = self.regs.sp.wrapping_add(db as u16);
+        self.regs.set_rp(de, 0x10);
+        self.regs.pc = self.regs.pc.wrapping_add(2);
+        10
+    }
+    pub(super) fn rstv<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        self.long_m1();
+        let pc = self.regs.pc.wrapping_add(1);
+        if self.v_flag() {
+            self.push_16(mem, pc);
+            self.regs.pc = 0x40;
+            return 12;
+        }
+        self.regs.pc = pc;
+        6
+    }
+    pub(super) fn shlx<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let de = self.regs.get_rp(0x10);
+        self.write(mem, de, self.regs.l);
+        self.write(mem, de.wrapping_add(1), self.regs.h);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        10
+    }
+    pub(super) fn lhlx<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let de = self.regs.get_rp(0x10);
+        self.regs.l = self.read(mem, de);
+        self.regs.h = self.read(mem, de.wrapping_add(1));
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        10
+    }
+    /// JNK (DD) and JK (FD).
+    pub(super) fn jnk<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        if self.k_flag() == (self.instruction & 0x20 == 0x20) {
+            let addr = self.get_16(mem);
+            self.regs.pc = addr;
+            return 10;
+        }
+        self.skip_16(mem);
+        self.regs.pc = self.regs.pc.wrapping_add(3);
+        7
+    }
+}
@@ -0,0 +1,44 @@
+use super::bus::MachineCycle;
+/// Port-mapped I/O seen by the `IN` and `OUT` instructions.
+pub trait IoBus {
+    /// Called by `IN port`; the returned byte is loaded into A.
+    fn read_port(&mut self, port: u8) -> u8;
+    /// Called by `OUT port` with the contents of A.
+    fn write_port(&mut self, port: u8, value: u8);
+    /// Called for each `IN`/`OUT` machine cycle after `Bus::cycle` has seen it.
+    /// Returns the wait states the port needs; READY is shared, so the longer
+    /// of this and the memory side's request wins. No waits by default.
+    fn cycle(&mut self, _cycle: MachineCycle) -> u8 {
+        0
+    }
+}
+/// Default port bus: a 256-byte input latch array and a strobe holding the last OUT.
+#[derive(Debug, Copy, Clone)]
+pub struct Ports {
+    input: [u8; 0x100],
+    pub out_strobe: (bool, u8, u8),
+}
+impl Ports {
+    pub fn new() -> Ports {
+        Ports {
+            input: [0x00; 0x100],
+            out_strobe: (false, 0, 0),
+        }
+    }
+    pub fn set_input_n(&mut self, n: u8, value: u8) {
+        self.input[n as usize] = value;
+    }
+}
+impl Default for Ports {
+    fn default() -> Self {
+        Ports::new()
+    }
+}
+impl IoBus for Ports {
+    fn read_port(&mut self, port: u8) -> u8 {
+        self.input[port as usize]
+    }
+    fn write_port(&mut self, port: u8, value: u8) {
+        self.out_strobe = (true, port, value);
+    }
+}
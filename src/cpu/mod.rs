@@ -1,746 +1,1114 @@
-#[cfg(feature = "std")]
-use std::*;
 pub mod regs;
-use core::{panic, fmt::LowerExp};
-use core::ops::{IndexMut, Index};
+pub mod io;
+pub mod bus;
+pub mod error;
+pub mod disasm;
+pub mod trace;
+pub mod builder;
+#[cfg(feature = "alloc")]
+pub mod snapshot;
+#[cfg(feature = "alloc")]
+pub mod history;
+#[cfg(feature = "alloc")]
+pub mod replay;
+#[cfg(feature = "asm")]
+pub mod asm;
+mod alu;
+#[cfg(feature = "i8085")]
+pub mod i8085;
 #[cfg(feature = "log")]
 use log::{debug, error, trace};
 use regs::Registers;
+use io::IoBus;
+use bus::{Bus, MachineCycle, Status};
+use error::CpuError;
+use core::marker::PhantomData;
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CPU {
     pub instruction: u8,
     pub regs: Registers,
-    cyc:u32,
-    input: [u8; 0x100],
-    pub out_strobe:(bool, u8, u8),
+    cycles:u64,
     pub interrupt_enabled:bool,
+    halted:bool,
+    interrupt_request:Option<[u8; 3]>,
+    ei_delay:bool,
+    faulted:bool,
+    breakpoints:[Option<u16>; MAX_BREAKPOINTS],
+    resume:Option<u16>,
+    variant:Variant,
+    t_state:u32,
+    wait_states:u32,
+    hold:bool,
+    hlda:bool,
+    #[cfg(feature = "log")]
+    operands:[u8; 2],
+    #[cfg(feature = "i8085")]
+    i8085:i8085::Interrupts8085,
+    /// The 8085's K and V flags, in their PSW positions 5 and 1.
+    #[cfg(feature = "i8085")]
+    kv:u8,
 }
+/// The processor model being emulated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Variant {
+    #[default]
+    I8080,
+    #[cfg(feature = "i8085")]
+    I8085,
+}
+pub const MAX_BREAKPOINTS: usize = 8;
+/// Outcome of an instruction completed by `CPU::try_step`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StepInfo {
+    pub pc: u16,
+    pub opcode: u8,
+    pub cycles: u32,
+}
+/// Why `CPU::run_for` or `CPU::run_until` returned.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// The cycle budget is used up; the last instruction ran `overshoot`
+    /// states past it. Subtract it from the next frame's budget.
+    Budget { overshoot: u64 },
+    /// The `run_until` predicate held after `cycles` states.
+    Condition { cycles: u64 },
+    /// The core stopped after `cycles` states, as `try_step` would report it.
+    /// A `Halted` reason is also given right after `HLT` executes.
+    Stopped { cycles: u64, reason: CpuError },
+}
+type Handler<M, I> = fn(&mut CPU, &mut M, &mut I) -> u8;
 impl CPU {
+    /// An 8080 with zeroed registers, then `start_pc` and `start_sp` if given.
+    /// They are applied with or without the `start_regs` feature, which is kept
+    /// only so existing manifests still build. See `CpuBuilder` for more options.
     pub fn new(start_pc: Option<u16>, start_sp: Option<u16>) -> CPU {
         let mut cpu = CPU {
             instruction: 0,
             regs: Registers::default(),
-            cyc: 0,
-            input: [0x00; 0x100],
-            out_strobe:(false, 0, 0),
-            interrupt_enabled:false
+            cycles: 0,
+            interrupt_enabled:false,
+            halted:false,
+            interrupt_request:None,
+            ei_delay:false,
+            faulted:false,
+            breakpoints:[None; MAX_BREAKPOINTS],
+            resume:None,
+            variant:Variant::I8080,
+            t_state:0,
+            wait_states:0,
+            hold:false,
+            hlda:false,
+            #[cfg(feature = "log")]
+            operands:[0; 2],
+            #[cfg(feature = "i8085")]
+            i8085:i8085::Interrupts8085::default(),
+            #[cfg(feature = "i8085")]
+            kv:0,
         };
-        #[cfg(feature = "start_regs")]
-        {
-            if let Some(pc) = start_pc{
-                cpu.regs.pc = pc;
-            };
-            if let Some(sp) = start_sp{
-                cpu.regs.sp = sp;
-            }
+        if let Some(pc) = start_pc{
+            cpu.regs.pc = pc;
+        };
+        if let Some(sp) = start_sp{
+            cpu.regs.sp = sp;
         }
         cpu
     }
-    pub fn set_input_n(&mut self, n: u8, value: u8) {
-        self.input[n as usize] = value;
+    pub fn with_variant(variant: Variant) -> CPU {
+        let mut cpu = CPU::new(None, None);
+        cpu.variant = variant;
+        cpu
+    }
+    /// Pulses RESET: PC returns to 0 and INTE, a pending EI and HLT are cleared.
+    /// The other registers keep their contents, as on the chip. The cycle
+    /// counter, breakpoints and an INTR request still held by a device are left
+    /// alone. On the 8085 the RST 5.5/6.5/7.5 inputs are masked again.
+    pub fn reset(&mut self) {
+        self.regs.pc = 0;
+        self.interrupt_enabled = false;
+        self.ei_delay = false;
+        self.halted = false;
+        self.faulted = false;
+        self.resume = None;
+        #[cfg(feature = "i8085")]
+        self.i8085.reset();
+    }
+    pub fn variant(&self) -> Variant {
+        self.variant
     }
     pub fn get_regs(&self) -> Registers {
         self.regs
     }
-    fn get_16(&self, mem: &mut dyn IndexMut<u16, Output=u8>) -> u16 {
-        let lb = mem[self.regs.pc + 1];
-        let hb = mem[self.regs.pc + 2];
+    /// F as `PUSH PSW` stores it. On the 8085 bits 5 and 1 carry K and V,
+    /// which `regs.f` keeps at their fixed 8080 values.
+    pub fn psw(&self) -> u8 {
+        match self.variant {
+            Variant::I8080 => self.regs.f.get(),
+            #[cfg(feature = "i8085")]
+            Variant::I8085 => self.regs.f.get() & !0x22 | self.kv,
+        }
+    }
+    /// Loads F as `POP PSW` does, taking K and V from bits 5 and 1 on the 8085.
+    pub fn set_psw(&mut self, f: u8) {
+        self.regs.f.set(f);
+        #[cfg(feature = "i8085")]
+        if self.variant == Variant::I8085 {
+            self.kv = f & 0x22;
+        }
+    }
+    /// Returns `true` while the core is stopped by `HLT` waiting for an interrupt.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+    /// Raises INTR. `instr` is the instruction the interrupting device places on the
+    /// data bus during INTA: usually a single RST opcode, or a CALL followed by its two
+    /// address bytes. An empty slice reads as `0xFF` (RST 7), like a pulled-up bus.
+    /// The request stays pending until `next` accepts it with interrupts enabled.
+    pub fn request_interrupt(&mut self, instr: &[u8]) {
+        self.interrupt_request = Some(bus_bytes(instr));
+    }
+    /// Runs `instr` through an interrupt acknowledge cycle right away, as if a
+    /// device had just placed it on the bus: an RST, a CALL, or any other opcode,
+    /// which then executes without moving PC past it. Unlike `request_interrupt`
+    /// this ignores INTE, but it still clears INTE and wakes the core from HLT.
+    /// Returns the states taken, which are added to the cycle counter.
+    pub fn inject<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, io:&mut I, instr: &[u8]) ->u32{
+        self.wait_states = 0;
+        let cyc = self.acknowledge(mem, io, bus_bytes(instr));
+        let cyc = self.end_of_instruction(mem, cyc);
+        self.cycles = self.cycles.wrapping_add(cyc as u64);
+        cyc
+    }
+    /// Takes the RST in `instruction` as an interrupt, pushing PC as it is.
+    #[deprecated(note = "use `CPU::inject` with the RST opcode")]
+    pub fn rst<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, io:&mut I) ->u32{
+        self.inject(mem, io, &[0xC7 | self.instruction & 0x38])
+    }
+    /// Drives the HOLD input from the host. It is seen between instructions:
+    /// while asserted the core raises HLDA and idles one state per `next`
+    /// call, leaving the bus to a DMA device. A device that takes the bus
+    /// between the machine cycles of an instruction asserts HOLD through
+    /// `Bus::hold` instead.
+    pub fn set_hold(&mut self, hold: bool) {
+        self.hold = hold;
+    }
+    /// Hold acknowledge: `true` while the core has given up the bus.
+    pub fn hlda(&self) -> bool {
+        self.hlda
+    }
+    /// Returns `true` while an interrupt request is waiting to be acknowledged.
+    pub fn interrupt_pending(&self) -> bool {
+        self.interrupt_request.is_some()
+    }
+    /// Sets a breakpoint checked by `try_step`. Returns `false` if all
+    /// `MAX_BREAKPOINTS` slots are in use.
+    pub fn set_breakpoint(&mut self, addr: u16) -> bool {
+        if self.breakpoints.contains(&Some(addr)) {
+            return true;
+        }
+        match self.breakpoints.iter_mut().find(|b| b.is_none()) {
+            Some(slot) => {
+                *slot = Some(addr);
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn clear_breakpoint(&mut self, addr: u16) {
+        for b in self.breakpoints.iter_mut().filter(|b| **b == Some(addr)) {
+            *b = None;
+        }
+    }
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints = [None; MAX_BREAKPOINTS];
+    }
+    /// Total states executed since creation or the last `reset_cycles`.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+    pub fn reset_cycles(&mut self) {
+        self.cycles = 0;
+    }
+    /// Runs until at least `cycles` states have elapsed, or until a halt,
+    /// breakpoint or undefined opcode stops the core early.
+    pub fn run_for<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, io:&mut I, cycles: u64) -> RunOutcome {
+        self.run(mem, io, Some(cycles), |_| false)
+    }
+    /// Runs until `done` returns `true` after an instruction, or until a halt,
+    /// breakpoint or undefined opcode stops the core.
+    pub fn run_until<M: Bus + ?Sized, I: IoBus + ?Sized, F: FnMut(&CPU) -> bool>(&mut self, mem:&mut M, io:&mut I, done: F) -> RunOutcome {
+        self.run(mem, io, None, done)
+    }
+    fn run<M: Bus + ?Sized, I: IoBus + ?Sized, F: FnMut(&CPU) -> bool>(&mut self, mem:&mut M, io:&mut I, budget: Option<u64>, mut done: F) -> RunOutcome {
+        let start = self.cycles;
+        loop {
+            let spent = self.cycles.wrapping_sub(start);
+            if let Some(budget) = budget {
+                if spent >= budget {
+                    return RunOutcome::Budget { overshoot: spent - budget };
+                }
+            }
+            if let Err(reason) = self.try_step(mem, io) {
+                return RunOutcome::Stopped { cycles: self.cycles.wrapping_sub(start), reason };
+            }
+            if self.halted {
+                let reason = CpuError::Halted { pc: self.regs.pc };
+                return RunOutcome::Stopped { cycles: self.cycles.wrapping_sub(start), reason };
+            }
+            if done(self) {
+                return RunOutcome::Condition { cycles: self.cycles.wrapping_sub(start) };
+            }
+        }
+    }
+    /// Like `next`, but reports breakpoints, halt idling and undefined opcodes
+    /// as errors instead of returning a bare cycle count.
+    pub fn try_step<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, io:&mut I) -> Result<StepInfo, CpuError> {
+        let pc = self.regs.pc;
+        if self.resume != Some(pc) && !self.halted && self.breakpoints.contains(&Some(pc)) {
+            self.resume = Some(pc);
+            return Err(CpuError::Breakpoint { pc });
+        }
+        self.resume = None;
+        let was_halted = self.halted;
+        let cycles = self.next(mem, io);
+        if self.hlda {
+            return Err(CpuError::Held { pc });
+        }
+        if self.faulted {
+            self.faulted = false;
+            return Err(CpuError::UndefinedOpcode { pc, opcode: self.instruction });
+        }
+        if was_halted && self.halted {
+            return Err(CpuError::Halted { pc });
+        }
+        Ok(StepInfo { pc, opcode: self.instruction, cycles })
+    }
+    /// Reports a machine cycle starting at the current T-state and moves past it.
+    fn bus_cycle<M: Bus + ?Sized>(&mut self, mem: &mut M, status: Status, addr: u16) {
+        self.hold_bus(mem);
+        let waits = mem.cycle(MachineCycle { t_state: self.t_state, status, addr });
+        self.wait(3, waits);
+    }
+    /// Like `bus_cycle` for `IN`/`OUT`, where the port may ask for waits too.
+    fn io_cycle<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem: &mut M, io: &mut I, status: Status, port: u8) {
+        self.hold_bus(mem);
+        let cycle = MachineCycle { t_state: self.t_state, status, addr: (port as u16) << 8 | port as u16 };
+        let waits = mem.cycle(cycle).max(io.cycle(cycle));
+        self.wait(3, waits);
+    }
+    /// Samples HOLD at the end of the previous machine cycle and idles, bus
+    /// released, for as long as the device holds it. The states are counted
+    /// with the wait states.
+    fn hold_bus<M: Bus + ?Sized>(&mut self, mem: &mut M) {
+        while mem.hold(self.t_state) {
+            self.t_state += 1;
+            self.wait_states += 1;
+        }
+    }
+    /// Samples HOLD after the last machine cycle, then returns the states the
+    /// instruction took: its `base` timing plus wait and hold states.
+    fn end_of_instruction<M: Bus + ?Sized>(&mut self, mem: &mut M, base: u8) -> u32 {
+        self.t_state = base as u32 + self.wait_states;
+        self.hold_bus(mem);
+        base as u32 + self.wait_states
+    }
+    fn wait(&mut self, states: u8, waits: u8) {
+        self.t_state += states as u32 + waits as u32;
+        self.wait_states += waits as u32;
+    }
+    fn read<M: Bus + ?Sized>(&mut self, mem: &mut M, addr: u16) -> u8 {
+        self.bus_cycle(mem, Status::MEMORY_READ, addr);
+        mem.read(addr)
+    }
+    fn write<M: Bus + ?Sized>(&mut self, mem: &mut M, addr: u16, val: u8) {
+        self.bus_cycle(mem, Status::MEMORY_WRITE, addr);
+        mem.write(addr, val);
+    }
+    fn stack_read<M: Bus + ?Sized>(&mut self, mem: &mut M, addr: u16) -> u8 {
+        self.bus_cycle(mem, Status::STACK_READ, addr);
+        mem.read(addr)
+    }
+    fn stack_write<M: Bus + ?Sized>(&mut self, mem: &mut M, addr: u16, val: u8) {
+        self.bus_cycle(mem, Status::STACK_WRITE, addr);
+        mem.write(addr, val);
+    }
+    /// Starts an instruction with its M1 cycle; `read` and friends then count
+    /// from the end of a 4 state M1.
+    fn m1_cycle<M: Bus + ?Sized>(&mut self, mem: &mut M, status: Status) {
+        self.t_state = 0;
+        let waits = mem.cycle(MachineCycle { t_state: 0, status, addr: self.regs.pc });
+        self.wait(4, waits);
+    }
+    /// Stretches M1 for instructions that spend extra states on it before their
+    /// first bus access: 5 on the 8080, 6 on the 8085.
+    fn long_m1(&mut self) {
+        self.t_state += self.timing(1, 2) as u32;
+    }
+    fn get_s<M: Bus + ?Sized>(&mut self, mem: &mut M) -> (u8, u8) {
+        if self.instruction & 0x07 == 6 {
+            return (self.read(mem, self.regs.get_rp(0x20)), 6);
+        }
+        self.regs.get_s(self.instruction, mem)
+    }
+    fn get_d<M: Bus + ?Sized>(&mut self, mem: &mut M) -> (u8, u8) {
+        if self.instruction & 0x38 == 0x30 {
+            return (self.read(mem, self.regs.get_rp(0x20)), 6);
+        }
+        self.regs.get_d(self.instruction, mem)
+    }
+    fn set_d<M: Bus + ?Sized>(&mut self, mem: &mut M, val: u8) -> u8 {
+        if self.instruction & 0x38 == 0x30 {
+            self.write(mem, self.regs.get_rp(0x20), val);
+            return 6;
+        }
+        self.regs.set_d(self.instruction, mem, val)
+    }
+    /// Reads the `n`th byte after the opcode. With `log` the bytes are kept
+    /// for the disassembled trace line.
+    fn operand<M: Bus + ?Sized>(&mut self, mem: &mut M, n: u16) -> u8 {
+        let val = self.read(mem, self.regs.pc.wrapping_add(n));
+        #[cfg(feature = "log")]
+        {
+            self.operands[n as usize - 1] = val;
+        }
+        val
+    }
+    fn get_16<M: Bus + ?Sized>(&mut self, mem: &mut M) -> u16 {
+        let lb = self.operand(mem, 1);
+        let hb = self.operand(mem, 2);
         (hb as u16) << 8 | lb as u16
     }
-    fn pop_16(&mut self, mem: &mut dyn IndexMut<u16, Output=u8>) -> u16 {
-        let lb = mem[self.regs.sp];
-        let hb = mem[self.regs.sp + 1];
-        self.regs.sp += 2;
+    /// Operand reads of a jump or call that is not taken: the 8080 still reads
+    /// both bytes, the 8085 only the first.
+    fn skip_16<M: Bus + ?Sized>(&mut self, mem: &mut M) {
+        self.operand(mem, 1);
+        if self.variant == Variant::I8080 {
+            self.read(mem, self.regs.pc.wrapping_add(2));
+        }
+    }
+    fn pop_16<M: Bus + ?Sized>(&mut self, mem: &mut M) -> u16 {
+        let lb = self.stack_read(mem, self.regs.sp);
+        let hb = self.stack_read(mem, self.regs.sp.wrapping_add(1));
+        self.regs.sp = self.regs.sp.wrapping_add(2);
         (hb as u16) << 8 | lb as u16
     }
-    pub fn next(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        #[cfg(feature = "log")]
-        debug!("PC: {:04X} ", self.regs.pc);
-        #[cfg(feature = "std")]{
-            print!("PC: {:04X}, ", self.regs.pc);
-            print!("AF: {:04X}, ", (self.regs.a as u16) << 8 | self.regs.f.get() as u16);
-            print!("BC: {:04X}, ", (self.regs.b as u16) << 8 | self.regs.c as u16);
-            print!("DE: {:04X}, ", (self.regs.d as u16) << 8 | self.regs.e as u16);
-            print!("HL: {:04X}, ", (self.regs.h as u16) << 8 | self.regs.l as u16);
-            print!("SP: {:04X}, ", self.regs.sp);
-            print!("CYC: {:04X} \n", self.cyc as u32);
+    fn push_16<M: Bus + ?Sized>(&mut self, mem: &mut M, val: u16) {
+        self.stack_write(mem, self.regs.sp.wrapping_sub(1), (val >> 8) as u8);
+        self.stack_write(mem, self.regs.sp.wrapping_sub(2), val as u8);
+        self.regs.sp = self.regs.sp.wrapping_sub(2);
+    }
+    pub fn next<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, io:&mut I) ->u32{
+        self.hlda = self.hold;
+        if self.hlda {
+            self.cycles = self.cycles.wrapping_add(1);
+            return 1;
         }
-        //#[cfg(feature = "std")]
-        //print!("PC: {:04X} ", self.regs.pc);
-        self.instruction = mem[self.regs.pc];
-        let cyc = LUT[self.instruction as usize](self, mem);
+        let accept = self.accepts_interrupts();
+        self.ei_delay = false;
+        self.wait_states = 0;
+        let cyc = if let Some(vector) = self.vectored_interrupt(accept) {
+            self.interrupt_enabled = false;
+            self.halted = false;
+            self.t_state = 4;
+            self.long_m1();
+            self.push_16(mem, self.regs.pc);
+            self.regs.pc = vector;
+            #[cfg(feature = "log")]
+            debug!("INTA {:04X}", vector);
+            self.timing(11, 12)
+        } else {
+            match self.interrupt_request {
+                Some(bytes) if accept => {
+                    self.interrupt_request = None;
+                    self.acknowledge(mem, io, bytes)
+                }
+                _ if self.halted => 4,
+                _ => {
+                    self.m1_cycle(mem, Status::FETCH);
+                    self.instruction = mem.fetch(self.regs.pc);
+                    #[cfg(feature = "log")]
+                    let pc = self.regs.pc;
+                    #[cfg(feature = "log")]
+                    {
+                        self.operands = [0; 2];
+                    }
+                    let cyc = self.dispatch(mem, io);
+                    #[cfg(feature = "log")]
+                    debug!("{:04X}  {}", pc, self.disassemble([self.instruction, self.operands[0], self.operands[1]]));
+                    cyc
+                }
+            }
+        };
         #[cfg(feature = "log")]
         trace!("{:X?}, {:X?}\n",self.instruction, self.regs);
-        //#[cfg(feature = "std")]
-        //print!("{:X?}\n", self.regs);
-        self.cyc += cyc as u32;
+        let cyc = self.end_of_instruction(mem, cyc);
+        self.cycles = self.cycles.wrapping_add(cyc as u64);
         cyc
     }
-    fn jmp(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn acknowledge<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, io:&mut I, bytes:[u8; 3]) ->u8{
+        self.interrupt_enabled = false;
+        let status = match self.halted {
+            true => Status::INTERRUPT_ACK.0 | Status::HLTA,
+            false => Status::INTERRUPT_ACK.0,
+        };
+        self.halted = false;
+        self.instruction = bytes[0];
+        // The injected instruction runs as if it sat just before PC, so that anything
+        // that does not branch leaves PC untouched and RST or CALL push the current PC.
+        let len = disasm::length(self.variant, self.instruction) as u16;
+        // INTA takes the place of the opcode fetch; PC is on the address bus.
+        self.m1_cycle(mem, Status(status));
+        let pc = self.regs.pc;
+        self.regs.pc = pc.wrapping_sub(len);
+        let mut bus = Injected { mem, base: self.regs.pc, len, bytes };
+        #[cfg(feature = "log")]
+        debug!("INTA {}", self.disassemble(bytes));
+        self.dispatch(&mut bus, io)
+    }
+    #[cfg(feature = "log")]
+    fn disassemble(&self, bytes: [u8; 3]) -> disasm::Instruction {
+        // Three bytes always cover an instruction.
+        disasm::decode(self.variant, &bytes).unwrap()
+    }
+    fn dispatch<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, io:&mut I) ->u8{
+        let op = self.instruction as usize;
+        match self.variant {
+            Variant::I8080 => Dispatch::<M, I>::LUT[op](self, mem, io),
+            #[cfg(feature = "i8085")]
+            Variant::I8085 => Dispatch::<M, I>::LUT_8085[op](self, mem, io),
+        }
+    }
+    /// Picks the 8080 or 8085 state count of the running variant.
+    fn timing(&self, i8080: u8, _i8085: u8) -> u8 {
+        match self.variant {
+            Variant::I8080 => i8080,
+            #[cfg(feature = "i8085")]
+            Variant::I8085 => _i8085,
+        }
+    }
+    /// Writes S, Z, P, AC and CY from an ALU result, plus V and K on the 8085.
+    fn set_alu_flags(&mut self, r: alu::AluResult) {
+        self.regs.set_flags(r.value, r.carry, r.aux);
+        if let Some(v) = r.overflow {
+            self.overflow(v, r.value);
+        }
+    }
+    /// INTE only reaches the interrupt logic after the instruction following EI.
+    fn accepts_interrupts(&self) -> bool {
+        self.interrupt_enabled && !self.ei_delay
+    }
+    /// The 8085 vector the next `next` call will jump to, if any.
+    #[cfg(feature = "alloc")]
+    fn pending_vector(&self) -> Option<u16> {
+        if self.hold {
+            return None;
+        }
+        let mut probe = *self;
+        probe.vectored_interrupt(self.accepts_interrupts())
+    }
+    #[cfg(not(feature = "i8085"))]
+    fn vectored_interrupt(&mut self, _accept: bool) -> Option<u16> {
+        None
+    }
+    #[cfg(all(feature = "alloc", not(feature = "i8085")))]
+    fn drive_vector(&mut self, _vector: u16, _level: bool) {}
+    #[cfg(not(feature = "i8085"))]
+    fn overflow(&mut self, _v: bool, _r: u8) {}
+    #[cfg(not(feature = "i8085"))]
+    fn carry_16(&mut self, _k: bool) {}
+    fn jmp<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
         let addr = self.get_16(mem);
         self.regs.pc = addr;
-        #[cfg(feature = "log")]
-        debug!("JMP {:04X}", addr);
         10
     }
-    fn lxi(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn lxi<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
         let val = self.get_16(mem);
         self.regs.set_rp(val, self.instruction);
-        self.regs.pc += 3;
-        #[cfg(feature = "log")]
-        debug!("LXI {:04X}", val);
+        self.regs.pc = self.regs.pc.wrapping_add(3);
         10
     }
-    fn ani(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let db = mem[self.regs.pc + 1];
-        self.regs.a &= db;
-        self.regs.set_flags(self.regs.a, false, false);
-        self.regs.pc += 2;
-        #[cfg(feature = "log")]
-        debug!("ANI {:02X}", db);
+    fn ani<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let db = self.operand(mem, 1);
+        let r = alu::and(self.regs.a, db, self.variant);
+        self.set_alu_flags(r);
+        self.regs.a = r.value;
+        self.regs.pc = self.regs.pc.wrapping_add(2);
         7
     }
-    fn jccc(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let mut addr = 0;
+    fn jccc<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
         if self.regs.cond(self.instruction) {
-            addr = self.get_16(mem);
+            let addr = self.get_16(mem);
             self.regs.pc = addr;
+            10
         } else {
-            self.regs.pc += 3;
+            self.skip_16(mem);
+            self.regs.pc = self.regs.pc.wrapping_add(3);
+            self.timing(10, 7)
         }
-        #[cfg(feature = "log")]
-        debug!("Jccc {:04X}", addr);
-        10
     }
-    fn adi(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let db = mem[self.regs.pc + 1];
-        let (a, v) = self.regs.a.overflowing_add(db);
-        let h = ((self.regs.a & 0xF) + (db & 0xF)) & 0x10 == 0x10;
-        self.regs.a = a;
-        self.regs.set_flags(self.regs.a, v, h);
-        self.regs.pc += 2;
-        #[cfg(feature = "log")]
-        debug!("ADI {:02X}", db);
+    fn adi<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let db = self.operand(mem, 1);
+        let r = alu::add(self.regs.a, db, false);
+        self.set_alu_flags(r);
+        self.regs.a = r.value;
+        self.regs.pc = self.regs.pc.wrapping_add(2);
         7
     }
-    fn call(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let pc = self.regs.pc.wrapping_add(3);
-        mem[self.regs.sp - 1] = (pc >> 8) as u8;
-        mem[self.regs.sp - 2] = pc as u8;
-        self.regs.sp -= 2;
+    fn call<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        self.long_m1();
         let addr = self.get_16(mem);
+        let pc = self.regs.pc.wrapping_add(3);
+        self.push_16(mem, pc);
         self.regs.pc = addr;
-        #[cfg(feature = "log")]
-        debug!("CALL {:04X}", addr);
-        17
+        self.timing(17, 18)
     }
-    fn push(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let rp = self.regs.get_rp(self.instruction);
-        mem[self.regs.sp - 1] = (rp >> 8) as u8;
-        mem[self.regs.sp - 2] = rp as u8;
-        self.regs.sp -= 2;
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("PUSH {:04X}", rp);
-        11
+    fn push<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        self.long_m1();
+        let rp = match self.instruction {
+            0xF5 => (self.regs.a as u16) << 8 | self.psw() as u16,
+            op => self.regs.get_rp(op),
+        };
+        self.push_16(mem, rp);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        self.timing(11, 12)
     }
-    fn xchg(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn xchg<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
         let hl = self.regs.get_rp(0x20);
         let de = self.regs.get_rp(0x10);
         self.regs.set_rp(hl, 0x10);
         self.regs.set_rp(de, 0x20);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("XCHG {:04X}", de);
-        5
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        4
     }
-    fn mvi(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let v = mem[self.regs.pc + 1];
-        let reg_idx = self.regs.set_d(self.instruction, mem, v);
-        self.regs.pc += 2;
-        #[cfg(feature = "log")]
-        debug!("MVI {:02X}", mem[self.regs.pc + 1]);
+    fn mvi<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let v = self.operand(mem, 1);
+        let reg_idx = self.set_d(mem, v);
+        self.regs.pc = self.regs.pc.wrapping_add(2);
         if reg_idx == 6 {
             return 10;
         };
         7
     }
-    fn nop(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("NOP {:04X}", self.regs.pc);
+    fn nop<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         4
     }
-    fn fault(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn fault<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
         #[cfg(feature = "log")]
         error!(
             "regs:{:x?}, instr:{:08b}, {:02x}",
             self.regs, self.instruction, self.instruction
         );
-        panic!("FAULT");
+        self.faulted = true;
+        4
     }
-    fn mov(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let (s, idx_src) = self.regs.get_s(self.instruction, mem);
-        let idx_dst = self.regs.set_d(self.instruction, mem, s);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("MOV {:02X}", s);
+    fn mov<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let (s, idx_src) = self.get_s(mem);
+        let idx_dst = self.set_d(mem, s);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         if idx_src == 6 || idx_dst == 6 {
             return 7;
         }
-        5
+        self.timing(5, 4)
     }
-    fn lda(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn lda<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
         let addr = self.get_16(mem);
-        self.regs.a = mem[addr];
-        self.regs.pc += 3;
-        #[cfg(feature = "log")]
-        debug!("LDA {:04X}", addr);
+        self.regs.a = self.read(mem, addr);
+        self.regs.pc = self.regs.pc.wrapping_add(3);
         13
     }
-    fn sda(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn sda<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
         let addr = self.get_16(mem);
-        mem[addr] = self.regs.a;
-        self.regs.pc += 3;
-        #[cfg(feature = "log")]
-        debug!("SDA {:04X}", addr);
+        self.write(mem, addr, self.regs.a);
+        self.regs.pc = self.regs.pc.wrapping_add(3);
         13
     }
-    fn lhld(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn lhld<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
         let addr = self.get_16(mem);
-        let val = (mem[addr + 1] as u16) << 8 | mem[addr] as u16;
+        let lb = self.read(mem, addr);
+        let val = (self.read(mem, addr.wrapping_add(1)) as u16) << 8 | lb as u16;
         self.regs.set_rp(val, 0x20);
-        self.regs.pc += 3;
-        #[cfg(feature = "log")]
-        debug!("LHLD {:04X}", val);
+        self.regs.pc = self.regs.pc.wrapping_add(3);
         16
     }
-    fn shld(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn shld<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
         let addr = self.get_16(mem);
         let val = self.regs.get_rp(0x20);
-        mem[addr] = val as u8;
-        mem[addr + 1] = (val >> 8) as u8;
-        self.regs.pc += 3;
-        #[cfg(feature = "log")]
-        debug!("SHLD {:04X}", val);
+        self.write(mem, addr, val as u8);
+        self.write(mem, addr.wrapping_add(1), (val >> 8) as u8);
+        self.regs.pc = self.regs.pc.wrapping_add(3);
         16
     }
-    fn ldax(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn ldax<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
         let rp = self.regs.get_rp(self.instruction);
-        self.regs.a = mem[rp];
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("LDAX {:04X}", rp);
+        self.regs.a = self.read(mem, rp);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         7
     }
-    fn stax(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn stax<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
         let rp = self.regs.get_rp(self.instruction);
-        mem[rp] = self.regs.a;
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("STAX {:04X}", rp);
+        self.write(mem, rp, self.regs.a);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         7
     }
-    fn add(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let (s, idx_src) = self.regs.get_s(self.instruction, mem);
-        let (a, v) = self.regs.a.overflowing_add(s);
-        let h = ((self.regs.a & 0xF) + (s & 0xF)) & 0x10 == 0x10;
-        self.regs.set_flags(a, v, h);
-        self.regs.a = a;
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("ADD {:02X}", s);
-        if idx_src == 6 {
+    fn add<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let (s, idx_src) = self.get_s(mem);
+        let r = alu::add(self.regs.a, s, false);
+        self.set_alu_flags(r);
+        self.regs.a = r.value;
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        if idx_src == 6{
             return 7;
         };
         4
     }
-    fn adc(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let (s,idx_src) = self.regs.get_s(self.instruction, mem);
-        let (a0, v0) = self.regs.a.overflowing_add(s);
-        let (a1, v1) = a0.overflowing_add(self.regs.f.get_carry() as u8);
-        let h = ((self.regs.a & 0xF) + (s & 0xF) + self.regs.f.get_carry() as u8) & 0x10 == 0x10;
-        self.regs.a = a1;
-        self.regs.set_flags(self.regs.a, v0 | v1, h);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("ADC {:02X}", mem[self.regs.pc + 1]);
+    fn adc<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let (s, idx_src) = self.get_s(mem);
+        let r = alu::add(self.regs.a, s, self.regs.f.get_carry());
+        self.set_alu_flags(r);
+        self.regs.a = r.value;
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         if idx_src == 6{
             return 7;
         };
         4
     }
-    fn aci(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let s = mem[self.regs.pc + 1];
-        let (a0, v0) = self.regs.a.overflowing_add(s);
-        let (a1, v1) = a0.overflowing_add(self.regs.f.get_carry() as u8);
-        let h = ((self.regs.a & 0xF) + (s & 0xF) + self.regs.f.get_carry() as u8) & 0x10 == 0x10;
-        self.regs.a = a1;
-        self.regs.set_flags(self.regs.a, v0 | v1, h);
-        self.regs.pc += 2;
-        #[cfg(feature = "log")]
-        debug!("ACI {:02X}", mem[self.regs.pc + 1]);
+    fn aci<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let db = self.operand(mem, 1);
+        let r = alu::add(self.regs.a, db, self.regs.f.get_carry());
+        self.set_alu_flags(r);
+        self.regs.a = r.value;
+        self.regs.pc = self.regs.pc.wrapping_add(2);
         7
     }
-    fn sub(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let (s, idx_src) = self.regs.get_s(self.instruction, mem);
-        let (a, v) = self.regs.a.overflowing_sub(s);
-        let h = (self.regs.a & 0xF).wrapping_sub(s & 0xF) & 0x10 == 0x10;
-        self.regs.set_flags(a, v, h);
-        self.regs.a = a;
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("SUB {:02X}", s);
+    fn sub<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let (s, idx_src) = self.get_s(mem);
+        let r = alu::sub(self.regs.a, s, false);
+        self.set_alu_flags(r);
+        self.regs.a = r.value;
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         if idx_src == 6{
             return 7;
         };
         4
     }
-    fn sui(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let s = mem[self.regs.pc + 1];
-        let (a, v) = self.regs.a.overflowing_sub(s);
-        let h = ((self.regs.a & 0xF) + (s & 0xF)) & 0x10 == 0x10;
-        self.regs.a = a;
-        self.regs.set_flags(self.regs.a, v, h);
-        self.regs.pc += 2;
-        #[cfg(feature = "log")]
-        debug!("SUI {:02X}", mem[self.regs.pc + 1]);
+    fn sui<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let db = self.operand(mem, 1);
+        let r = alu::sub(self.regs.a, db, false);
+        self.set_alu_flags(r);
+        self.regs.a = r.value;
+        self.regs.pc = self.regs.pc.wrapping_add(2);
         7
     }
-    fn sbb(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let (s, idx_src) = self.regs.get_s(self.instruction, mem);
-        let (a0, v0) = self.regs.a.overflowing_sub(s);
-        let (a1, v1) = a0.overflowing_sub(self.regs.f.get_carry() as u8);
-        let h = (self.regs.a & 0xF)
-            .wrapping_sub(s & 0xF)
-            .wrapping_sub(self.regs.f.get_carry() as u8)
-            & 0x10
-            == 0x10;
-        self.regs.a = a1;
-        self.regs.set_flags(self.regs.a, v0 | v1, h);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("SBB {:02X}", mem[self.regs.pc + 1]);
+    fn sbb<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let (s, idx_src) = self.get_s(mem);
+        let r = alu::sub(self.regs.a, s, self.regs.f.get_carry());
+        self.set_alu_flags(r);
+        self.regs.a = r.value;
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         if idx_src == 6{
             return 7;
         };
         4
     }
-    fn sbi(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let s = mem[self.regs.pc + 1];
-        let (a0, v0) = self.regs.a.overflowing_sub(s);
-        let (a1, v1) = a0.overflowing_sub(self.regs.f.get_carry() as u8);
-        let h = (self.regs.a & 0xF)
-            .wrapping_sub(s & 0xF)
-            .wrapping_sub(self.regs.f.get_carry() as u8)
-            & 0x10
-            == 0x10;
-        self.regs.a = a1;
-        self.regs.set_flags(self.regs.a, v0 | v1, h);
-        self.regs.pc += 2;
-        #[cfg(feature = "log")]
-        debug!("SBI {:02X}", mem[self.regs.pc + 1]);
+    fn sbi<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let db = self.operand(mem, 1);
+        let r = alu::sub(self.regs.a, db, self.regs.f.get_carry());
+        self.set_alu_flags(r);
+        self.regs.a = r.value;
+        self.regs.pc = self.regs.pc.wrapping_add(2);
         7
     }
-    fn inr(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let (r, idx_src) = self.regs.get_d(self.instruction, mem);
-        let i = r.wrapping_add(1);
-        let h = ((r & 0xF) + 1) & 0x10 == 0x10;
-        self.regs.set_d(self.instruction, mem, i);
-        self.regs.set_flags(i, false, h);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("INR {:02X}", r);
+    fn inr<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let (v, idx_src) = self.get_d(mem);
+        let r = alu::inr(v);
+        self.set_d(mem, r.value);
+        self.set_alu_flags(alu::AluResult { carry: self.regs.f.get_carry(), ..r });
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         if idx_src == 6{
             return 10;
         };
-        5
-    }
-    fn dcr(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let (r, idx_src) = self.regs.get_d(self.instruction, mem);
-        let i = r.wrapping_sub(1);
-        let h = ((r & 0xF).wrapping_sub(1)) & 0x10 == 0x10;
-        self.regs.set_d(self.instruction, mem, i);
-        self.regs.set_flags(i, false, h);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("DCR {:02X}", i);
+        self.timing(5, 4)
+    }
+    fn dcr<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let (v, idx_src) = self.get_d(mem);
+        let r = alu::dcr(v);
+        self.set_d(mem, r.value);
+        self.set_alu_flags(alu::AluResult { carry: self.regs.f.get_carry(), ..r });
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         if idx_src == 6{
             return 10;
         };
-        5
+        self.timing(5, 4)
     }
-    fn inx(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn inx<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
         let rp = self.regs.get_rp(self.instruction);
         self.regs.set_rp(rp.wrapping_add(1), self.instruction);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("INX {:02x}", rp);
-        5
+        self.carry_16(rp == 0xFFFF);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        self.timing(5, 6)
     }
-    fn dcx(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn dcx<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
         let rp = self.regs.get_rp(self.instruction);
         self.regs.set_rp(rp.wrapping_sub(1), self.instruction);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("DCX {:02x}", rp);
-        5
+        self.carry_16(rp == 0x0000);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        self.timing(5, 6)
     }
-    fn dad(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn dad<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
         let rp = self.regs.get_rp(self.instruction);
         let hl = self.regs.get_rp(0x20);
         let (hl, v) = hl.overflowing_add(rp);
         self.regs.set_rp(hl, 0x20);
         self.regs.f.set_carry(v);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("DAD {:04x}", hl);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         10
     }
-    fn daa(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let mut acc = self.regs.a;
-        let mut low_nib = (acc & 0x0F) as u8;
-        if low_nib > 9 || self.get_regs().f.get_aux(){
-            low_nib += 6;
-            self.regs.f.set_aux(low_nib > 0x0F);
-            acc += 6;
-        }
-        let mut up_nib = acc & 0xF0;
-        if up_nib > 0x90 || self.get_regs().f.get_carry(){
-            let (a, v) = acc.overflowing_add(0x60);
-            self.regs.f.set_carry(v);
-            acc = a
-        }
-        self.regs.set_s(7, mem, acc);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        error!("DAA");
-        //panic!("DAA at addr {:02X}", self.regs.pc);
+    fn daa<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
+        let r = alu::daa(self.regs.a, self.regs.f.get_carry(), self.regs.f.get_aux());
+        self.set_alu_flags(r);
+        self.regs.a = r.value;
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         4
     }
-    fn ana(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let (s,idx_src) = self.regs.get_s(self.instruction, mem);
-        self.regs.a &= s;
-        self.regs.set_flags(self.regs.a, false, false);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("ANA {:02X}", s);
+    fn ana<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let (s, idx_src) = self.get_s(mem);
+        let r = alu::and(self.regs.a, s, self.variant);
+        self.set_alu_flags(r);
+        self.regs.a = r.value;
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         if idx_src == 6{
             return 7;
         };
         4
     }
-    fn ora(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let (s, idx_src) = self.regs.get_s(self.instruction, mem);
-        self.regs.a |= s;
-        self.regs.set_flags(self.regs.a, false, false);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("ORA {:02X}", s);
+    fn ora<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let (s, idx_src) = self.get_s(mem);
+        let r = alu::or(self.regs.a, s);
+        self.set_alu_flags(r);
+        self.regs.a = r.value;
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         if idx_src == 6{
             return 7;
         };
         4
     }
-    fn ori(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        self.regs.a |= mem[self.regs.pc + 1];
-        self.regs.set_flags(self.regs.a, false, false);
-        self.regs.pc += 2;
-        #[cfg(feature = "log")]
-        debug!("ORI {:02X}", mem[self.regs.pc + 1]);
+    fn ori<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let db = self.operand(mem, 1);
+        let r = alu::or(self.regs.a, db);
+        self.set_alu_flags(r);
+        self.regs.a = r.value;
+        self.regs.pc = self.regs.pc.wrapping_add(2);
         7
     }
-    fn xra(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let (s, idx_src) = self.regs.get_s(self.instruction, mem);
-        self.regs.a ^= s;
-        self.regs.set_flags(self.regs.a, false, false);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("XRA {:02X}", s);
+    fn xra<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let (s, idx_src) = self.get_s(mem);
+        let r = alu::xor(self.regs.a, s);
+        self.set_alu_flags(r);
+        self.regs.a = r.value;
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         if idx_src == 6{
             return 7;
         };
         4
     }
-    fn xri(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        self.regs.a ^= mem[self.regs.pc + 1];
-        self.regs.set_flags(self.regs.a, false, false);
-        self.regs.pc += 2;
-        #[cfg(feature = "log")]
-        debug!("XRI {:02X}", mem[self.regs.pc + 1]);
+    fn xri<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let db = self.operand(mem, 1);
+        let r = alu::xor(self.regs.a, db);
+        self.set_alu_flags(r);
+        self.regs.a = r.value;
+        self.regs.pc = self.regs.pc.wrapping_add(2);
         7
     }
-    fn cmp(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let (s, idx_src) = self.regs.get_s(self.instruction, mem);
-        let h = (self.regs.a & 0xF).wrapping_sub(s) & 0x10 == 0x10;
-        let (a, v) = self.regs.a.overflowing_sub(s);
-        self.regs.set_flags(a, v, h);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("CMP {:02X}", mem[self.regs.pc + 1]);
+    fn cmp<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let (s, idx_src) = self.get_s(mem);
+        let r = alu::sub(self.regs.a, s, false);
+        self.set_alu_flags(r);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         if idx_src == 6{
             return 7;
         };
         4
     }
-    fn cpi(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let s = mem[self.regs.pc + 1];
-        let h = (self.regs.a & 0xF).wrapping_sub(s) & 0x10 != 0x10;
-        let (a, v) = self.regs.a.overflowing_sub(s);
-        self.regs.set_flags(a, v, h);
-        self.regs.pc += 2;
-        #[cfg(feature = "log")]
-        debug!("CPI {:02X}", mem[self.regs.pc + 1]);
+    fn cpi<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        let db = self.operand(mem, 1);
+        let r = alu::sub(self.regs.a, db, false);
+        self.set_alu_flags(r);
+        self.regs.pc = self.regs.pc.wrapping_add(2);
         7
     }
-    fn rlc(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn rlc<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
         let (a, c) = self.regs.a.overflowing_mul(2);
         self.regs.a = a + c as u8;
         self.regs.f.set_carry(c);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("RLC {:02x}", a);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         4
     }
-    fn rrc(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn rrc<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
         let a = self.regs.a.rotate_right(1);
         self.regs.a = a;
         self.regs.f.set_carry(a & 0x80 == 0x80);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("RRC {:02x}", a);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         4
     }
-    fn ral(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn ral<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
         let (a, c) = self.regs.a.overflowing_mul(2);
         self.regs.a = a + self.regs.f.get_carry() as u8;
         self.regs.f.set_carry(c);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("RAL {:02x}", a);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         4
     }
-    fn rar(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn rar<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
+        let a = self.regs.a >> 1 | (self.regs.f.get_carry() as u8) << 7;
         self.regs.f.set_carry(self.regs.a & 1 == 1);
-        let a = (self.regs.a as i8) >> 1;
-        self.regs.a = a as u8;
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("RAL {:02x}", a);
+        self.regs.a = a;
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         4
     }
-    fn cma(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn cma<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
         self.regs.a = !self.regs.a;
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("CMA {:02x}", self.regs.a);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         4
     }
-    fn cmc(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn cmc<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
         let c = !self.regs.f.get_carry();
         self.regs.f.set_carry(c);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("CMC {}", self.regs.f.get_carry());
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         4
     }
-    fn stc(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn stc<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
         self.regs.f.set_carry(true);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("STC");
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         4
     }
-    fn c_ccc(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let mut addr = 0;
+    fn c_ccc<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        self.long_m1();
         if self.regs.cond(self.instruction) {
-            mem[self.regs.sp - 1] = (self.regs.pc >> 8) as u8;
-            mem[self.regs.sp - 2] = self.regs.pc as u8;
-            self.regs.sp -= 2;
-            addr = self.get_16(mem);
+            let addr = self.get_16(mem);
+            let pc = self.regs.pc.wrapping_add(3);
+            self.push_16(mem, pc);
             self.regs.pc = addr;
-            #[cfg(feature = "log")]
-            debug!("Cccc {:04X}", addr);
-            return 17;
+            return self.timing(17, 18);
         }
-        self.regs.pc += 3;
-        11
-
+        self.skip_16(mem);
+        self.regs.pc = self.regs.pc.wrapping_add(3);
+        self.timing(11, 9)
     }
-    pub fn ret(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    pub fn ret<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
         let addr = self.pop_16(mem);
         self.regs.pc = addr;
-        #[cfg(feature = "log")]
-        debug!("RET {:04X}", addr);
         10
     }
-    fn r_ccc(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let mut addr = 0;
+    fn r_ccc<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        self.long_m1();
         if self.regs.cond(self.instruction) {
-            addr = self.pop_16(mem);
+            let addr = self.pop_16(mem);
             self.regs.pc = addr;
-            #[cfg(feature = "log")]
-            debug!("Rccc {:04X}", addr);
-            return 11
+            return self.timing(11, 12);
         }
-        self.regs.pc += 1;
-        5
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        self.timing(5, 6)
     }
-    pub fn rst(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        mem[self.regs.sp - 1] = (self.regs.pc >> 8) as u8;
-        mem[self.regs.sp - 2] = self.regs.pc as u8;
-        self.regs.sp -= 2;
+    fn rst_n<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        self.long_m1();
+        self.push_16(mem, self.regs.pc.wrapping_add(1));
         let addr = self.instruction & 0b00111000;
         self.regs.pc = addr as u16;
-        #[cfg(feature = "log")]
-        debug!("RST {:02X}", addr);
-        11
+        self.timing(11, 12)
     }
-    fn pchl(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        #[cfg(feature = "log")]
-        debug!("PCHL {:04x}", self.regs.pc);
+    fn pchl<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
         self.regs.pc = self.regs.get_rp(0x20);
-        5
+        self.timing(5, 6)
     }
-    fn pop(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn pop<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
         let val = self.pop_16(mem);
-        self.regs.set_rp(val, self.instruction);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("POP {:04x}", val);
+        match self.instruction {
+            0xF1 => {
+                self.regs.a = (val >> 8) as u8;
+                self.set_psw(val as u8);
+            }
+            op => self.regs.set_rp(val, op),
+        }
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         10
     }
-    fn xthl(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn xthl<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
         let l = self.regs.l;
         let h = self.regs.h;
-        self.regs.l = mem[self.regs.sp];
-        self.regs.h = mem[self.regs.sp + 1];
-        mem[self.regs.sp] = l;
-        mem[self.regs.sp + 1] = h;
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("XTHL {:04x}", self.regs.get_rp(0x20));
-        18
-    }
-    fn sphl(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+        self.regs.l = self.stack_read(mem, self.regs.sp);
+        self.regs.h = self.stack_read(mem, self.regs.sp.wrapping_add(1));
+        self.stack_write(mem, self.regs.sp.wrapping_add(1), h);
+        self.stack_write(mem, self.regs.sp, l);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        self.timing(18, 16)
+    }
+    fn sphl<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
         self.regs.sp = self.regs.get_rp(0x20);
-        self.regs.pc += 1;
-        #[cfg(feature = "log")]
-        debug!("SPHL {:04x}", self.regs.get_rp(0x20));
-        5
-    }
-    fn r#in(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        let addr = mem[self.regs.pc+1];
-        let acc = self.input[addr as usize];
-        self.regs.set_s(7, mem, acc);
-        self.regs.pc +=2;
-        #[cfg(feature = "log")]
-        error!("IN {:02X}",acc);
-        //#[cfg(feature = "std")]
-        //panic!("IN at addr {:02X}", addr);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        self.timing(5, 6)
+    }
+    fn r#in<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, io:&mut I) ->u8{
+        let addr = self.operand(mem, 1);
+        self.io_cycle(mem, io, Status::INPUT_READ, addr);
+        let acc = io.read_port(addr);
+        self.regs.a = acc;
+        self.regs.pc = self.regs.pc.wrapping_add(2);
         10
     }
-    fn out(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn out<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, io:&mut I) ->u8{
         let acc = self.regs.a;
-        let addr = mem[self.regs.pc+1];
-        self.out_strobe = (true, addr, acc);
-        self.regs.pc +=2;
-        #[cfg(feature = "log")]
-        debug!("OUT {:02X}", acc);
+        let addr = self.operand(mem, 1);
+        self.io_cycle(mem, io, Status::OUTPUT_WRITE, addr);
+        io.write_port(addr, acc);
+        self.regs.pc = self.regs.pc.wrapping_add(2);
         10
     }
-    fn ei(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn ei<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
         self.interrupt_enabled = true;
-        self.regs.pc+=1;
-        #[cfg(feature = "log")]
-        debug!("EI");
-        //#[cfg(feature = "std")]
-        //panic!("EI at addr {:04X}", self.regs.pc);
+        self.ei_delay = true;
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         4
     }
-    fn di(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
+    fn di<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, _mem:&mut M, _io:&mut I) ->u8{
         self.interrupt_enabled = false;
-        self.regs.pc+=1;
-        #[cfg(feature = "log")]
-        debug!("DI");
-        //#[cfg(feature = "std")]
-        //panic!("DI at addr {:04X}", self.regs.pc);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
         4
     }
-    fn hlt(&mut self, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8{
-        #[cfg(feature = "log")]
-        error!("HLT");
-        unreachable!();
-        //#[cfg(feature = "std")]
-        //panic!("HLT at addr {:04X}", self.regs.pc);
+    fn hlt<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, _io:&mut I) ->u8{
+        self.halted = true;
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        self.bus_cycle(mem, Status::HALT_ACK, self.regs.pc);
+        self.timing(7, 5)
     }
 }
-const INDEX: [(&str, fn(&mut CPU, mem:&mut dyn IndexMut<u16, Output=u8>) ->u8); 57] = [
-    ("01DDDSSS", CPU::mov),
-    ("00DDD110", CPU::mvi),
-    ("00RP0001", CPU::lxi),
-    ("00RP1010", CPU::ldax),
-    ("00RP0010", CPU::stax),
-    ("00111010", CPU::lda),
-    ("00110010", CPU::sda),
-    ("00101010", CPU::lhld),
-    ("00100010", CPU::shld),
-    ("11101011", CPU::xchg),
-    ("10000SSS", CPU::add),
-    ("11000110", CPU::adi),
-    ("10001SSS", CPU::adc),
-    ("11001110", CPU::aci),
-    ("10010SSS", CPU::sub),
-    ("11010110", CPU::sui),
-    ("10011SSS", CPU::sbb),
-    ("11011110", CPU::sbi),
-    ("00DDD100", CPU::inr),
-    ("00DDD101", CPU::dcr),
-    ("00RP0011", CPU::inx),
-    ("00RP1011", CPU::dcx),
-    ("00RP1001", CPU::dad),
-    ("00100111", CPU::daa),
-    ("10100SSS", CPU::ana),
-    ("11100110", CPU::ani),
-    ("10110SSS", CPU::ora),
-    ("11110110", CPU::ori),
-    ("10101SSS", CPU::xra),
-    ("11101110", CPU::xri),
-    ("10111SSS", CPU::cmp),
-    ("11111110", CPU::cpi),
-    ("00000111", CPU::rlc),
-    ("00001111", CPU::rrc),
-    ("00010111", CPU::ral),
-    ("00011111", CPU::rar),
-    ("00101111", CPU::cma),
-    ("00111111", CPU::cmc),
-    ("00110111", CPU::stc),
-    ("1100N011", CPU::jmp),
-    ("11CCC010", CPU::jccc),
-    ("11NN1101", CPU::call),
-    ("11CCC100", CPU::c_ccc),
-    ("110N1001", CPU::ret),
-    ("11CCC000", CPU::r_ccc),
-    ("11NNN111", CPU::rst),
-    ("11101001", CPU::pchl),
-    ("11RP0101", CPU::push),
-    ("11RP0001", CPU::pop),
-    ("11100011", CPU::xthl),
-    ("11111001", CPU::sphl),
-    ("11011011", CPU::r#in),
-    ("11010011", CPU::out),
-    ("11111011", CPU::ei),
-    ("11110011", CPU::di),
-    ("01110110", CPU::hlt),
-    ("00NNN000", CPU::nop),
-];
-const LUT: [fn(&mut CPU, mem:&mut dyn IndexMut<u16, Output=u8>)->u8; 0x100] = index();
-const fn recursive(
-    lut: &mut [fn(&mut CPU, mem:&mut dyn IndexMut<u16, Output=u8>)->u8; 0x100],
-    kmask: u8,
-    xmask: u8,
-    val: fn(&mut CPU, &mut dyn IndexMut<u16, Output=u8>) -> u8,
-) {
+/// The three bytes a device drives during INTA; missing ones read as RST 7's
+/// pulled-up `0xFF` for the opcode and zero after it.
+fn bus_bytes(instr: &[u8]) -> [u8; 3] {
+    let mut bytes = [0xFF, 0x00, 0x00];
+    for (b, i) in bytes.iter_mut().zip(instr) {
+        *b = *i;
+    }
+    bytes
+}
+/// Memory as seen by an instruction supplied during INTA: its `len` bytes at
+/// `base` come from the device, everything else from memory.
+struct Injected<'a, M: ?Sized> {
+    mem: &'a mut M,
+    base: u16,
+    len: u16,
+    bytes: [u8; 3],
+}
+impl<M: Bus + ?Sized> Bus for Injected<'_, M> {
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr.wrapping_sub(self.base) {
+            off if off < self.len => self.bytes[off as usize],
+            _ => self.mem.read(addr),
+        }
+    }
+    fn peek(&mut self, addr: u16) -> u8 {
+        match addr.wrapping_sub(self.base) {
+            off if off < self.len => self.bytes[off as usize],
+            _ => self.mem.peek(addr),
+        }
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        self.mem.write(addr, val);
+    }
+    fn hold(&mut self, t_state: u32) -> bool {
+        self.mem.hold(t_state)
+    }
+    fn cycle(&mut self, mut cycle: MachineCycle) -> u8 {
+        if cycle.addr.wrapping_sub(self.base) < self.len && cycle.status.is_memr() {
+            cycle.status = Status(Status::INTA | Status::WO);
+        }
+        self.mem.cycle(cycle)
+    }
+}
+/// Opcode dispatch tables, monomorphized for each memory and I/O bus type.
+/// An `INDEX` entry: bit pattern, Intel and Zilog operand templates for `disasm`, handler.
+type Opcode<M, I> = (&'static str, &'static str, &'static str, Handler<M, I>);
+struct Dispatch<M: ?Sized, I: ?Sized>(PhantomData<Handler<M, I>>);
+impl<M: Bus + ?Sized, I: IoBus + ?Sized> Dispatch<M, I> {
+    const INDEX: [Opcode<M, I>; 57] = [
+        ("01DDDSSS", "MOV {d},{s}", "LD {d},{s}", CPU::mov),
+        ("00DDD110", "MVI {d},{b}", "LD {d},{b}", CPU::mvi),
+        ("00RP0001", "LXI {rp},{w}", "LD {rp},{w}", CPU::lxi),
+        ("00RP1010", "LDAX {rp}", "LD A,({rp})", CPU::ldax),
+        ("00RP0010", "STAX {rp}", "LD ({rp}),A", CPU::stax),
+        ("00111010", "LDA {w}", "LD A,({w})", CPU::lda),
+        ("00110010", "STA {w}", "LD ({w}),A", CPU::sda),
+        ("00101010", "LHLD {w}", "LD HL,({w})", CPU::lhld),
+        ("00100010", "SHLD {w}", "LD ({w}),HL", CPU::shld),
+        ("11101011", "XCHG", "EX DE,HL", CPU::xchg),
+        ("10000SSS", "ADD {s}", "ADD A,{s}", CPU::add),
+        ("11000110", "ADI {b}", "ADD A,{b}", CPU::adi),
+        ("10001SSS", "ADC {s}", "ADC A,{s}", CPU::adc),
+        ("11001110", "ACI {b}", "ADC A,{b}", CPU::aci),
+        ("10010SSS", "SUB {s}", "SUB {s}", CPU::sub),
+        ("11010110", "SUI {b}", "SUB {b}", CPU::sui),
+        ("10011SSS", "SBB {s}", "SBC A,{s}", CPU::sbb),
+        ("11011110", "SBI {b}", "SBC A,{b}", CPU::sbi),
+        ("00DDD100", "INR {d}", "INC {d}", CPU::inr),
+        ("00DDD101", "DCR {d}", "DEC {d}", CPU::dcr),
+        ("00RP0011", "INX {rp}", "INC {rp}", CPU::inx),
+        ("00RP1011", "DCX {rp}", "DEC {rp}", CPU::dcx),
+        ("00RP1001", "DAD {rp}", "ADD HL,{rp}", CPU::dad),
+        ("00100111", "DAA", "DAA", CPU::daa),
+        ("10100SSS", "ANA {s}", "AND {s}", CPU::ana),
+        ("11100110", "ANI {b}", "AND {b}", CPU::ani),
+        ("10110SSS", "ORA {s}", "OR {s}", CPU::ora),
+        ("11110110", "ORI {b}", "OR {b}", CPU::ori),
+        ("10101SSS", "XRA {s}", "XOR {s}", CPU::xra),
+        ("11101110", "XRI {b}", "XOR {b}", CPU::xri),
+        ("10111SSS", "CMP {s}", "CP {s}", CPU::cmp),
+        ("11111110", "CPI {b}", "CP {b}", CPU::cpi),
+        ("00000111", "RLC", "RLCA", CPU::rlc),
+        ("00001111", "RRC", "RRCA", CPU::rrc),
+        ("00010111", "RAL", "RLA", CPU::ral),
+        ("00011111", "RAR", "RRA", CPU::rar),
+        ("00101111", "CMA", "CPL", CPU::cma),
+        ("00111111", "CMC", "CCF", CPU::cmc),
+        ("00110111", "STC", "SCF", CPU::stc),
+        ("1100N011", "JMP {w}", "JP {w}", CPU::jmp),   // CB is an undocumented JMP
+        ("11CCC010", "J{c} {w}", "JP {c},{w}", CPU::jccc),
+        ("11NN1101", "CALL {w}", "CALL {w}", CPU::call),  // DD, ED and FD are undocumented CALLs
+        ("11CCC100", "C{c} {w}", "CALL {c},{w}", CPU::c_ccc),
+        ("110N1001", "RET", "RET", CPU::ret),   // D9 is an undocumented RET
+        ("11CCC000", "R{c}", "RET {c}", CPU::r_ccc),
+        ("11NNN111", "RST {n}", "RST {n}", CPU::rst_n),
+        ("11101001", "PCHL", "JP (HL)", CPU::pchl),
+        ("11RP0101", "PUSH {rp}", "PUSH {rp}", CPU::push),
+        ("11RP0001", "POP {rp}", "POP {rp}", CPU::pop),
+        ("11100011", "XTHL", "EX (SP),HL", CPU::xthl),
+        ("11111001", "SPHL", "LD SP,HL", CPU::sphl),
+        ("11011011", "IN {b}", "IN A,({b})", CPU::r#in),
+        ("11010011", "OUT {b}", "OUT ({b}),A", CPU::out),
+        ("11111011", "EI", "EI", CPU::ei),
+        ("11110011", "DI", "DI", CPU::di),
+        ("01110110", "HLT", "HALT", CPU::hlt),
+        ("00NNN000", "NOP", "NOP", CPU::nop),   // 08, 10, ..., 38 are undocumented NOPs
+    ];
+    const LUT: [Handler<M, I>; 0x100] = index(&Self::INDEX);
+    /// 8085 instructions that take over the 8080's undocumented opcode slots.
+    #[cfg(feature = "i8085")]
+    const INDEX_8085: [Opcode<M, I>; 11] = [
+        ("00001000", "DSUB", "DSUB", CPU::dsub),
+        ("00010000", "ARHL", "ARHL", CPU::arhl),
+        ("00011000", "RDEL", "RDEL", CPU::rdel),
+        ("00100000", "RIM", "RIM", CPU::rim),
+        ("00101000", "LDHI {b}", "LDHI {b}", CPU::ldhi),
+        ("00110000", "SIM", "SIM", CPU::sim),
+        ("00111000", "LDSI {b}", "LDSI {b}", CPU::ldsi),
+        ("11001011", "RSTV", "RSTV", CPU::rstv),
+        ("11011001", "SHLX", "SHLX", CPU::shlx),
+        ("11101101", "LHLX", "LHLX", CPU::lhlx),
+        ("11N11101", "J{k} {w}", "JP {k},{w}", CPU::jnk),
+    ];
+    #[cfg(feature = "i8085")]
+    const LUT_8085: [Handler<M, I>; 0x100] = extend(Self::LUT, &Self::INDEX_8085);
+}
+const fn recursive<T: Copy>(lut: &mut [T; 0x100], kmask: u8, xmask: u8, val: T) {
     if xmask == 0 {
         //debug!("kmask:{:03X}", kmask);
         lut[kmask as usize] = val;
@@ -751,7 +1119,7 @@ const fn recursive(
         recursive(lut, kmask | xmask_lsb, xmask_without_lsb, val);
     }
 }
-const fn place(lut: &mut [fn(&mut CPU, mem:&mut dyn IndexMut<u16, Output=u8>)->u8; 0x100], s: &str, v: fn(&mut CPU, &mut dyn IndexMut<u16, Output=u8>) -> u8) {
+const fn place<T: Copy>(lut: &mut [T; 0x100], s: &str, v: T) {
     let mut xmask: u8 = 0;
     let mut kmask: u8 = 0;
     let mut i = 0;
@@ -769,15 +1137,14 @@ const fn place(lut: &mut [fn(&mut CPU, mem:&mut dyn IndexMut<u16, Output=u8>)->u
     recursive(lut, kmask, xmask, v);
 }
 
-const fn index() -> [fn(&mut CPU, &mut dyn IndexMut<u16, Output=u8>) -> u8; 256] {
-    let mut lut: [fn(&mut CPU, &mut dyn IndexMut<u16, Output=u8>) -> u8;256] = [CPU::fault; 0x100];
+const fn index<M: Bus + ?Sized, I: IoBus + ?Sized>(table: &[Opcode<M, I>]) -> [Handler<M, I>; 256] {
+    extend([CPU::fault; 0x100], table)
+}
+const fn extend<M: ?Sized, I: ?Sized>(mut lut: [Handler<M, I>; 256], table: &[Opcode<M, I>]) -> [Handler<M, I>; 256] {
     let mut i = 0;
-    while i < INDEX.len() {
-        place(&mut lut, INDEX[i].0, INDEX[i].1);
+    while i < table.len() {
+        place(&mut lut, table[i].0, table[i].3);
         i = i + 1;
     }
     lut
 }
-
-
-
@@ -1,17 +1,30 @@
-#[derive(Copy, Clone, Debug)]
-pub struct Flags(u8);
+/// The 8080 PSW flag byte, laid out `SZ0A0P1C`. Every write goes through
+/// `store`, so bit 1 always reads 1 and bits 3 and 5 always read 0, whatever
+/// `POP PSW` or a host puts in. The 8085's K and V flags in bits 5 and 1 are
+/// kept by the `CPU`; see `CPU::psw`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "u8", into = "u8"))]
+pub struct Flags{
+    bits:u8,
+}
+/// Bits that always read 0 and 1 on the 8080.
+const FIXED_ZERO: u8 = 0x28;
+const FIXED_ONE: u8 = 0x02;
 impl Flags {
+    fn store(&mut self, s:u8){
+        self.bits = s & !FIXED_ZERO | FIXED_ONE;
+    }
     pub fn set(&mut self,s:u8){
-        self.0 = s;
+        self.store(s);
     }
     pub fn get(&self) ->u8{
-        self.0
+        self.bits
     }
     fn sets(&mut self,val:bool,shift:u8){
         let val = (val as u8) << shift;
         let mask = 1 << shift;
-        self.0&=!mask;
-        self.0|=val;
+        self.store(self.bits & !mask | val);
     }
     pub fn set_sign(&mut self, val: bool) {
         self.sets(val,7);
@@ -29,7 +42,7 @@ impl Flags {
         self.sets(val,0);
     }
     fn gets(self,shift:u8) ->bool{
-        let val = self.0 >> shift;
+        let val = self.bits >> shift;
         val&1 == 1
     }
     pub fn get_sign(&self) -> bool{
@@ -50,6 +63,51 @@ impl Flags {
 }
 impl Default for Flags {
     fn default() -> Self {
-        Flags(2)
+        Flags { bits: FIXED_ONE }
+    }
+}
+/// Loads a flag byte through `set`, so the fixed bits are forced.
+impl From<u8> for Flags {
+    fn from(s: u8) -> Flags {
+        let mut f = Flags::default();
+        f.set(s);
+        f
+    }
+}
+impl From<Flags> for u8 {
+    fn from(f: Flags) -> u8 {
+        f.get()
+    }
+}
+/// The documented flags by name, for building or rendering an `SZ-A-P-C` display.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct FlagBits {
+    pub sign: bool,
+    pub zero: bool,
+    pub aux: bool,
+    pub parity: bool,
+    pub carry: bool,
+}
+impl From<Flags> for FlagBits {
+    fn from(f: Flags) -> FlagBits {
+        FlagBits {
+            sign: f.get_sign(),
+            zero: f.get_zero(),
+            aux: f.get_aux(),
+            parity: f.get_pairity(),
+            carry: f.get_carry(),
+        }
+    }
+}
+/// Builds 8080 flags; the fixed bits take their constant values.
+impl From<FlagBits> for Flags {
+    fn from(b: FlagBits) -> Flags {
+        let mut f = Flags::default();
+        f.set_sign(b.sign);
+        f.set_zero(b.zero);
+        f.set_aux(b.aux);
+        f.set_parity(b.parity);
+        f.set_carry(b.carry);
+        f
     }
 }
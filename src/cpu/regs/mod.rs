@@ -1,8 +1,9 @@
 pub mod flags;
 
-use core::ops::IndexMut;
+use super::bus::Bus;
 use flags::Flags;
 #[derive(Clone,Copy,Debug,Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Registers{
     pub a:u8,
     pub b:u8,
@@ -38,15 +39,14 @@ impl Registers{
             0b00 => {(self.b as u16) << 8 | self.c as u16},
             0b01 => {(self.d as u16) << 8 | self.e as u16},
             0b10 => {(self.h as u16) << 8 | self.l as u16},
-            0b11 => if instr == 0xF5 || instr == 0xF1{
+            _ => if instr == 0xF5 || instr == 0xF1{
                 (self.a as u16) << 8 | self.f.get() as u16
             }else{
                 self.sp
             },
-            _=> {panic!()},
         }
     }
-    pub fn get_d(&mut self,i:u8,mem:&mut dyn IndexMut<u16, Output=u8>)->(u8,u8){
+    pub fn get_d<M: Bus + ?Sized>(&mut self,i:u8,mem:&mut M)->(u8,u8){
         let reg = (i & 0x38) >> 3;
         (
             match reg{
@@ -56,14 +56,13 @@ impl Registers{
                 3 => self.e,
                 4 => self.h,
                 5 => self.l,
-                6 => mem[self.get_rp(0x20)],
-                7 => self.a,
-                _ =>  {panic!()}
+                6 => mem.read(self.get_rp(0x20)),
+                _ => self.a,
             }
             ,reg
         )
     }
-    pub fn set_d(&mut self,i:u8,mem:&mut dyn IndexMut<u16, Output=u8>,val:u8)->u8{
+    pub fn set_d<M: Bus + ?Sized>(&mut self,i:u8,mem:&mut M,val:u8)->u8{
         let reg = (i & 0x38) >> 3;
         match reg{
             0 => self.b = val,
@@ -72,13 +71,13 @@ impl Registers{
             3 => self.e = val,
             4 => self.h = val,
             5 => self.l = val,
-            6 => mem[self.get_rp(0x20)] = val,
+            6 => mem.write(self.get_rp(0x20), val),
             7 => self.a = val,
             _ => {}
         };
         reg
     }
-    pub fn get_s(&mut self,i:u8,mem:&mut dyn IndexMut<u16, Output=u8>)->(u8, u8){
+    pub fn get_s<M: Bus + ?Sized>(&mut self,i:u8,mem:&mut M)->(u8, u8){
         let reg = i & 7;
         (match i & 7{
             0 => self.b,
@@ -87,12 +86,11 @@ impl Registers{
             3 => self.e,
             4 => self.h,
             5 => self.l,
-            6 => mem[self.get_rp(0x20)],
-            7 => self.a,
-            _ =>  {panic!()}
+            6 => mem.read(self.get_rp(0x20)),
+            _ => self.a,
         }, reg)
     }
-    pub fn set_s(&mut self,i:u8,mem:&mut dyn IndexMut<u16, Output=u8>,val:u8){
+    pub fn set_s<M: Bus + ?Sized>(&mut self,i:u8,mem:&mut M,val:u8){
         match i & 7{
             0 => self.b = val,
             1 => self.c = val,
@@ -100,7 +98,7 @@ impl Registers{
             3 => self.e = val,
             4 => self.h = val,
             5 => self.l = val,
-            6 => mem[self.get_rp(0x20)] = val,
+            6 => mem.write(self.get_rp(0x20), val),
             7 => self.a = val,
             _ => {}
         };
@@ -121,8 +119,7 @@ impl Registers{
             4 => !self.f.get_pairity(),
             5 => self.f.get_pairity(),
             6 => !self.f.get_sign(),
-            7 => self.f.get_sign(),
-            _ =>  {panic!()},
+            _ => self.f.get_sign(),
         }
     }
 }
\ No newline at end of file
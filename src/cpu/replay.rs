@@ -0,0 +1,247 @@
+//! Deterministic replay of the host's inputs. `CPU::step_recording` logs every
+//! value returned to IN, every INTR request accepted and every 8085 TRAP or
+//! RST 5.5/6.5/7.5 interrupt taken, and `CPU::inject_recording` logs injected
+//! instructions, all stamped with the cycle counter and PC at the start of the
+//! step; `CPU::step_replaying` feeds them back from the log and reports the
+//! first step at which the run stops matching it. The 8085 pin levels RIM
+//! reads, and SID, are not recorded.
+use alloc::vec::Vec;
+use core::fmt;
+use super::bus::{Bus, MachineCycle};
+use super::io::IoBus;
+use super::CPU;
+
+/// One input from the host, stamped with the step it was used in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Event {
+    /// `CPU::cycles` before the step.
+    pub cycle: u64,
+    pub pc: u16,
+    pub kind: EventKind,
+}
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EventKind {
+    /// IN read `value` from `port`.
+    Input { port: u8, value: u8 },
+    /// The INTR request with these bus bytes was acknowledged.
+    Interrupt { bytes: [u8; 3] },
+    /// `CPU::inject_recording` ran the instruction in these bus bytes.
+    Injected { bytes: [u8; 3] },
+    /// The 8085 took TRAP, RST 5.5, 6.5 or 7.5, jumping to `vector`.
+    Vectored { vector: u16 },
+}
+/// The inputs of a recorded run, oldest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Recording {
+    pub events: Vec<Event>,
+}
+impl Recording {
+    pub fn new() -> Recording {
+        Recording::default()
+    }
+}
+/// The first step at which a replayed run differed from its recording.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// PC and `CPU::cycles` at the start of the step.
+    pub pc: u16,
+    pub cycle: u64,
+    /// The event the recording expected next, if any was left.
+    pub expected: Option<Event>,
+}
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "replay diverged at {:04X}, cycle {}", self.pc, self.cycle)?;
+        match self.expected {
+            Some(e) => write!(f, "; expected {:?} at {:04X}, cycle {}", e.kind, e.pc, e.cycle),
+            None => write!(f, "; recording exhausted"),
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for Divergence {}
+/// Plays a `Recording` back through `CPU::step_replaying`.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    recording: Recording,
+    next: usize,
+    divergence: Option<Divergence>,
+}
+impl Replay {
+    pub fn new(recording: Recording) -> Replay {
+        Replay { recording, next: 0, divergence: None }
+    }
+    /// Events not yet fed back.
+    pub fn remaining(&self) -> &[Event] {
+        &self.recording.events[self.next..]
+    }
+    /// True once every event has been fed back without a divergence.
+    pub fn is_finished(&self) -> bool {
+        self.divergence.is_none() && self.remaining().is_empty()
+    }
+    /// The first divergence seen, kept for later steps.
+    pub fn divergence(&self) -> Option<Divergence> {
+        self.divergence
+    }
+    fn peek(&self) -> Option<Event> {
+        self.recording.events.get(self.next).copied()
+    }
+    /// Ends a step that started at `pc` and `cycle`, failing on `mismatch` or
+    /// when the next event is stamped before `now` and can no longer be matched.
+    fn finish(&mut self, pc: u16, cycle: u64, now: u64, mut mismatch: Option<Option<Event>>) -> Result<(), Divergence> {
+        if mismatch.is_none() && self.peek().is_some_and(|e| e.cycle < now) {
+            mismatch = Some(self.peek());
+        }
+        match mismatch {
+            Some(expected) => {
+                let d = Divergence { pc, cycle, expected };
+                self.divergence = Some(d);
+                Err(d)
+            }
+            None => Ok(()),
+        }
+    }
+}
+/// I/O wrapper logging the values read from the host.
+struct InputRecorder<'a, I: ?Sized> {
+    io: &'a mut I,
+    events: &'a mut Vec<Event>,
+    cycle: u64,
+    pc: u16,
+}
+impl<I: IoBus + ?Sized> IoBus for InputRecorder<'_, I> {
+    fn read_port(&mut self, port: u8) -> u8 {
+        let value = self.io.read_port(port);
+        self.events.push(Event { cycle: self.cycle, pc: self.pc, kind: EventKind::Input { port, value } });
+        value
+    }
+    fn write_port(&mut self, port: u8, value: u8) {
+        self.io.write_port(port, value);
+    }
+    fn cycle(&mut self, cycle: MachineCycle) -> u8 {
+        self.io.cycle(cycle)
+    }
+}
+/// I/O wrapper answering reads from the recording. Writes still reach the
+/// host. A read the recording does not expect returns FFH, as an undriven bus.
+struct InputPlayer<'a, I: ?Sized> {
+    io: &'a mut I,
+    replay: &'a mut Replay,
+    cycle: u64,
+    pc: u16,
+    /// Set on a mismatch to the event expected instead.
+    mismatch: Option<Option<Event>>,
+}
+impl<I: IoBus + ?Sized> IoBus for InputPlayer<'_, I> {
+    fn read_port(&mut self, port: u8) -> u8 {
+        match self.replay.peek() {
+            Some(Event { cycle, pc, kind: EventKind::Input { port: p, value } })
+                if cycle == self.cycle && pc == self.pc && p == port =>
+            {
+                self.replay.next += 1;
+                value
+            }
+            expected => {
+                self.mismatch.get_or_insert(expected);
+                0xFF
+            }
+        }
+    }
+    fn write_port(&mut self, port: u8, value: u8) {
+        self.io.write_port(port, value);
+    }
+    fn cycle(&mut self, cycle: MachineCycle) -> u8 {
+        self.io.cycle(cycle)
+    }
+}
+impl CPU {
+    /// `next`, logging its IN values and any interrupt it took to `recording`.
+    pub fn step_recording<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, io:&mut I, recording:&mut Recording) ->u32{
+        let (cycle, pc, request, vector) = (self.cycles, self.regs.pc, self.interrupt_request, self.pending_vector());
+        // The acknowledge comes before any IN the injected instruction makes.
+        let at = recording.events.len();
+        let mut ports = InputRecorder { io, events: &mut recording.events, cycle, pc };
+        let cycles = self.next(mem, &mut ports);
+        // A vectored interrupt goes first and leaves INTR pending.
+        let kind = match (vector, request, self.interrupt_request) {
+            (Some(vector), _, _) => Some(EventKind::Vectored { vector }),
+            (None, Some(bytes), None) => Some(EventKind::Interrupt { bytes }),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            recording.events.insert(at, Event { cycle, pc, kind });
+        }
+        cycles
+    }
+    /// `inject`, logging the instruction and its IN values to `recording`.
+    /// `step_replaying` replays it as a step of its own.
+    pub fn inject_recording<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, io:&mut I, instr: &[u8], recording:&mut Recording) ->u32{
+        let (cycle, pc) = (self.cycles, self.regs.pc);
+        recording.events.push(Event { cycle, pc, kind: EventKind::Injected { bytes: super::bus_bytes(instr) } });
+        let mut ports = InputRecorder { io, events: &mut recording.events, cycle, pc };
+        self.inject(mem, &mut ports, instr)
+    }
+    /// `next`, with IN values and interrupts taken from `replay` instead of
+    /// the host; `io` only sees writes and bus cycles. A recorded `inject` is
+    /// run in place of `next`. Start from the state the recording started from
+    /// and make no `request_interrupt` or `inject` calls, nor drive the 8085
+    /// interrupt pins, of your own. Fails at the first step that reads a port,
+    /// takes an interrupt or passes a recorded event differently from the
+    /// recording, and at every step after it.
+    pub fn step_replaying<M: Bus + ?Sized, I: IoBus + ?Sized>(&mut self, mem:&mut M, io:&mut I, replay:&mut Replay) -> Result<u32, Divergence> {
+        if let Some(d) = replay.divergence {
+            return Err(d);
+        }
+        let (cycle, pc) = (self.cycles, self.regs.pc);
+        let due = replay.peek().filter(|e| e.cycle == cycle && e.pc == pc);
+        if let Some(Event { kind: EventKind::Injected { bytes }, .. }) = due {
+            replay.next += 1;
+            let mut ports = InputPlayer { io, replay, cycle, pc, mismatch: None };
+            let cycles = self.inject(mem, &mut ports, &bytes);
+            let mismatch = ports.mismatch;
+            return replay.finish(pc, cycle, self.cycles, mismatch).map(|()| cycles);
+        }
+        let (interrupt, vectored) = match due {
+            Some(e @ Event { kind: EventKind::Interrupt { bytes }, .. }) => {
+                self.request_interrupt(&bytes);
+                (Some(e), None)
+            }
+            Some(e @ Event { kind: EventKind::Vectored { vector }, .. }) => {
+                self.drive_vector(vector, true);
+                (None, Some((e, vector)))
+            }
+            _ => (None, None),
+        };
+        if interrupt.is_some() || vectored.is_some() {
+            replay.next += 1;
+        }
+        let (request, vector) = (self.interrupt_request, self.pending_vector());
+        let mut ports = InputPlayer { io, replay, cycle, pc, mismatch: None };
+        let cycles = self.next(mem, &mut ports);
+        let mut mismatch = ports.mismatch;
+        match vectored {
+            Some((e, v)) => {
+                // Released again, and a latch that was not taken is dropped.
+                self.drive_vector(v, false);
+                if vector != Some(v) {
+                    mismatch.get_or_insert(Some(e));
+                }
+            }
+            None if vector.is_some() => {
+                mismatch.get_or_insert(replay.peek());
+            }
+            None => {}
+        }
+        if interrupt.is_some() && self.interrupt_request.is_some() {
+            // Not accepted this time; withdraw it rather than leave it pending.
+            self.interrupt_request = None;
+            mismatch = Some(interrupt);
+        } else if interrupt.is_none() && request.is_some() && self.interrupt_request.is_none() {
+            mismatch.get_or_insert(replay.peek());
+        }
+        replay.finish(pc, cycle, self.cycles, mismatch).map(|()| cycles)
+    }
+}
@@ -0,0 +1,179 @@
+//! Machine state snapshots in a versioned, little-endian byte format that
+//! does not depend on the host or on which cargo features are enabled.
+//!
+//! Layout of version 1:
+//!
+//! | bytes | field |
+//! |-------|-------|
+//! | 4     | magic `I80S` |
+//! | 2     | format version |
+//! | 1     | variant: 0 8080, 1 8085 |
+//! | 8     | A B C D E H L, then F as `PUSH PSW` stores it |
+//! | 2, 2  | SP, PC |
+//! | 1     | last opcode |
+//! | 8     | cycle counter |
+//! | 1     | INTE, HLT, EI pending, faulted, breakpoint at PC resumed, HOLD, HLDA (bit 0 up) |
+//! | 4     | INTR request: present, then three bus bytes |
+//! | 1     | breakpoint slots *n*, then *n* × (present, address) |
+//! | 4, 4  | T-state and wait states of the current instruction |
+//! | 3     | 8085 mask, input and pin bits, INTE saved by TRAP |
+//! | 4     | memory length, then the memory bytes |
+use alloc::vec::Vec;
+use core::fmt;
+use super::{Variant, CPU, MAX_BREAKPOINTS};
+
+const MAGIC: &[u8; 4] = b"I80S";
+pub const VERSION: u16 = 1;
+/// A CPU together with the memory it runs in.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    pub cpu: CPU,
+    pub memory: Vec<u8>,
+}
+/// Why `Snapshot::load` rejected its input.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    BadMagic,
+    /// Written by a newer version of the format.
+    UnsupportedVersion(u16),
+    /// An 8085 snapshot loaded without the `i8085` feature, or an unknown variant.
+    UnsupportedVariant(u8),
+    Truncated,
+    /// Bytes left over after the memory image.
+    TrailingBytes,
+}
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::BadMagic => write!(f, "not a snapshot"),
+            SnapshotError::UnsupportedVersion(v) => write!(f, "unsupported snapshot version {}", v),
+            SnapshotError::UnsupportedVariant(v) => write!(f, "unsupported variant {}", v),
+            SnapshotError::Truncated => write!(f, "snapshot truncated"),
+            SnapshotError::TrailingBytes => write!(f, "trailing bytes after snapshot"),
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for SnapshotError {}
+impl Snapshot {
+    pub fn new(cpu: &CPU, memory: &[u8]) -> Snapshot {
+        Snapshot { cpu: *cpu, memory: memory.to_vec() }
+    }
+    pub fn save(&self) -> Vec<u8> {
+        let c = &self.cpu;
+        let r = &c.regs;
+        let mut out = Vec::with_capacity(64 + MAX_BREAKPOINTS * 3 + self.memory.len());
+        out.extend(MAGIC);
+        out.extend(VERSION.to_le_bytes());
+        out.push(match c.variant {
+            Variant::I8080 => 0,
+            #[cfg(feature = "i8085")]
+            Variant::I8085 => 1,
+        });
+        out.extend([r.a, r.b, r.c, r.d, r.e, r.h, r.l, c.psw()]);
+        out.extend(r.sp.to_le_bytes());
+        out.extend(r.pc.to_le_bytes());
+        out.push(c.instruction);
+        out.extend(c.cycles.to_le_bytes());
+        let resume = c.resume == Some(r.pc);
+        let bits = [c.interrupt_enabled, c.halted, c.ei_delay, c.faulted, resume, c.hold, c.hlda];
+        out.push(bits.iter().enumerate().fold(0, |acc, (i, b)| acc | (*b as u8) << i));
+        match c.interrupt_request {
+            Some(bytes) => {
+                out.push(1);
+                out.extend(bytes);
+            }
+            None => out.extend([0; 4]),
+        }
+        out.push(MAX_BREAKPOINTS as u8);
+        for bp in c.breakpoints {
+            out.push(bp.is_some() as u8);
+            out.extend(bp.unwrap_or(0).to_le_bytes());
+        }
+        out.extend(c.t_state.to_le_bytes());
+        out.extend(c.wait_states.to_le_bytes());
+        #[cfg(feature = "i8085")]
+        out.extend(c.i8085.to_bytes());
+        // The 8085 power-on state, so the bytes do not depend on the features.
+        #[cfg(not(feature = "i8085"))]
+        out.extend([0x07, 0, 0]);
+        out.extend((self.memory.len() as u32).to_le_bytes());
+        out.extend(&self.memory);
+        out
+    }
+    pub fn load(bytes: &[u8]) -> Result<Snapshot, SnapshotError> {
+        let mut r = Reader(bytes);
+        if r.take(4)? != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        match r.u16()? {
+            VERSION => {}
+            v => return Err(SnapshotError::UnsupportedVersion(v)),
+        }
+        let variant = match r.u8()? {
+            0 => Variant::I8080,
+            #[cfg(feature = "i8085")]
+            1 => Variant::I8085,
+            v => return Err(SnapshotError::UnsupportedVariant(v)),
+        };
+        let mut c = CPU::with_variant(variant);
+        let regs = r.take(8)?;
+        let g = &mut c.regs;
+        [g.a, g.b, g.c, g.d, g.e, g.h, g.l] = [regs[0], regs[1], regs[2], regs[3], regs[4], regs[5], regs[6]];
+        g.sp = r.u16()?;
+        g.pc = r.u16()?;
+        c.set_psw(regs[7]);
+        c.instruction = r.u8()?;
+        c.cycles = u64::from_le_bytes(r.array()?);
+        let bits = r.u8()?;
+        let bit = |i: u8| bits >> i & 1 != 0;
+        (c.interrupt_enabled, c.halted, c.ei_delay, c.faulted) = (bit(0), bit(1), bit(2), bit(3));
+        c.resume = bit(4).then_some(c.regs.pc);
+        (c.hold, c.hlda) = (bit(5), bit(6));
+        let request: [u8; 4] = r.array()?;
+        c.interrupt_request = (request[0] != 0).then(|| [request[1], request[2], request[3]]);
+        // Slots beyond this build's limit are dropped.
+        for i in 0..r.u8()? as usize {
+            let set = r.u8()? != 0;
+            let addr = r.u16()?;
+            if let Some(slot) = c.breakpoints.get_mut(i) {
+                *slot = set.then_some(addr);
+            }
+        }
+        c.t_state = u32::from_le_bytes(r.array()?);
+        c.wait_states = u32::from_le_bytes(r.array()?);
+        let _i8085: [u8; 3] = r.array()?;
+        #[cfg(feature = "i8085")]
+        {
+            c.i8085 = super::i8085::Interrupts8085::from_bytes(_i8085);
+        }
+        let len = u32::from_le_bytes(r.array()?) as usize;
+        let memory = r.take(len)?.to_vec();
+        if !r.0.is_empty() {
+            return Err(SnapshotError::TrailingBytes);
+        }
+        Ok(Snapshot { cpu: c, memory })
+    }
+}
+struct Reader<'a>(&'a [u8]);
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.0.len() < n {
+            return Err(SnapshotError::Truncated);
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        // `take` returned exactly N bytes.
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+}
@@ -0,0 +1,204 @@
+//! Opt-in execution tracing. `CPU::step_traced` runs one step through
+//! recording bus wrappers and hands a `TraceRecord` of the instruction to a
+//! `Tracer`; plain `next` pays nothing for it.
+use core::fmt;
+use super::bus::{Bus, MachineCycle, Status};
+use super::disasm::{self, Instruction};
+use super::io::IoBus;
+use super::regs::Registers;
+use super::CPU;
+
+/// More bus cycles than any one instruction makes; CALL, XTHL and LHLD make five.
+pub const MAX_ACCESSES: usize = 6;
+/// One bus cycle of a traced instruction. I/O cycles carry the port on both
+/// address halves, as on the real bus.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Access {
+    pub status: Status,
+    pub addr: u16,
+    pub value: u8,
+}
+/// Everything one instruction did. `Display` gives the reference line
+/// `PC: 0100, AF: 0002, BC: 0000, DE: 0000, HL: 0000, SP: 0000, CYC: 0`
+/// with the registers and cycle counter from before it ran.
+#[derive(Debug, Copy, Clone)]
+pub struct TraceRecord {
+    pub pc: u16,
+    /// The instruction bytes; an interrupt's come from the device.
+    pub bytes: [u8; 3],
+    pub instruction: Instruction,
+    pub before: Registers,
+    pub after: Registers,
+    accesses: [Access; MAX_ACCESSES],
+    count: usize,
+    /// States the instruction took, wait states included.
+    pub cycles: u32,
+    /// `CPU::cycles` before the instruction.
+    pub total_cycles: u64,
+}
+impl TraceRecord {
+    /// The instruction's bus cycles in order, the opcode fetch or INTA first.
+    pub fn accesses(&self) -> &[Access] {
+        &self.accesses[..self.count]
+    }
+    /// The opcode and its operand bytes.
+    pub fn opcode_bytes(&self) -> &[u8] {
+        &self.bytes[..self.instruction.len as usize]
+    }
+}
+impl fmt::Display for TraceRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let r = &self.before;
+        write!(
+            f,
+            "PC: {:04X}, AF: {:04X}, BC: {:04X}, DE: {:04X}, HL: {:04X}, SP: {:04X}, CYC: {}",
+            self.pc,
+            (r.a as u16) << 8 | r.f.get() as u16,
+            r.get_rp(0x00),
+            r.get_rp(0x10),
+            r.get_rp(0x20),
+            r.sp,
+            self.total_cycles
+        )
+    }
+}
+/// Receives a record for every instruction run by `CPU::step_traced`.
+pub trait Tracer {
+    fn trace(&mut self, record: &TraceRecord);
+}
+impl<F: FnMut(&TraceRecord)> Tracer for F {
+    fn trace(&mut self, record: &TraceRecord) {
+        self(record)
+    }
+}
+/// Writes one reference line per instruction to a `fmt::Write`, such as a
+/// `String`. Write errors are dropped.
+#[derive(Debug, Default)]
+pub struct LineTracer<W>(pub W);
+impl<W: fmt::Write> Tracer for LineTracer<W> {
+    fn trace(&mut self, record: &TraceRecord) {
+        let _ = writeln!(self.0, "{}", record);
+    }
+}
+/// Writes one reference line per instruction to an `io::Write`, such as
+/// stdout or a file. Write errors are dropped.
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct IoTracer<W>(pub W);
+#[cfg(feature = "std")]
+impl<W: std::io::Write> Tracer for IoTracer<W> {
+    fn trace(&mut self, record: &TraceRecord) {
+        let _ = writeln!(self.0, "{}", record);
+    }
+}
+/// Memory wrapper logging every cycle with the byte that went over the bus.
+struct Recorder<'a, M: ?Sized> {
+    mem: &'a mut M,
+    accesses: [Access; MAX_ACCESSES],
+    count: usize,
+}
+impl<M: ?Sized> Recorder<'_, M> {
+    fn value(&mut self, val: u8) -> u8 {
+        if let Some(a) = self.count.checked_sub(1).map(|i| &mut self.accesses[i]) {
+            a.value = val;
+        }
+        val
+    }
+}
+impl<M: Bus + ?Sized> Bus for Recorder<'_, M> {
+    fn read(&mut self, addr: u16) -> u8 {
+        let val = self.mem.read(addr);
+        self.value(val)
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        self.value(val);
+        self.mem.write(addr, val);
+    }
+    fn peek(&mut self, addr: u16) -> u8 {
+        self.mem.peek(addr)
+    }
+    fn fetch(&mut self, addr: u16) -> u8 {
+        let val = self.mem.fetch(addr);
+        self.value(val)
+    }
+    fn hold(&mut self, t_state: u32) -> bool {
+        self.mem.hold(t_state)
+    }
+    fn cycle(&mut self, cycle: MachineCycle) -> u8 {
+        if self.count < MAX_ACCESSES {
+            self.accesses[self.count] = Access { status: cycle.status, addr: cycle.addr, value: 0 };
+            self.count += 1;
+        }
+        self.mem.cycle(cycle)
+    }
+}
+/// I/O wrapper keeping the byte of the (single) port access.
+struct PortRecorder<'a, I: ?Sized> {
+    io: &'a mut I,
+    value: u8,
+}
+impl<I: IoBus + ?Sized> IoBus for PortRecorder<'_, I> {
+    fn read_port(&mut self, port: u8) -> u8 {
+        self.value = self.io.read_port(port);
+        self.value
+    }
+    fn write_port(&mut self, port: u8, value: u8) {
+        self.value = value;
+        self.io.write_port(port, value);
+    }
+    fn cycle(&mut self, cycle: MachineCycle) -> u8 {
+        self.io.cycle(cycle)
+    }
+}
+impl CPU {
+    /// `next`, reporting the instruction to `tracer`. Steps that run none,
+    /// such as idling in HLT, holding the bus or an 8085 vectored interrupt,
+    /// are not reported.
+    pub fn step_traced<M: Bus + ?Sized, I: IoBus + ?Sized, T: Tracer + ?Sized>(&mut self, mem:&mut M, io:&mut I, tracer:&mut T) ->u32{
+        let (pc, before, total_cycles) = (self.regs.pc, self.regs, self.cycles);
+        let request = self.interrupt_request;
+        // Decoded up front: a branch the 8085 does not take reads only part
+        // of its operand, so the bus cycles cannot rebuild it.
+        let fetched = disasm::decode_at(self.variant, mem, pc);
+        let mut bus = Recorder {
+            mem,
+            accesses: [Access { status: Status(0), addr: 0, value: 0 }; MAX_ACCESSES],
+            count: 0,
+        };
+        let mut ports = PortRecorder { io, value: 0 };
+        let cycles = self.next(&mut bus, &mut ports);
+        let (mut accesses, count) = (bus.accesses, bus.count);
+        if count == 0 || !accesses[0].status.is_m1() {
+            return cycles;
+        }
+        let (bytes, instruction) = if accesses[0].status.is_inta() {
+            // The device supplies the bytes; memory never sees them.
+            let bytes = request.unwrap_or([self.instruction, 0, 0]);
+            let inta = accesses[..count].iter_mut().filter(|a| a.status.is_inta());
+            for (a, b) in inta.zip(bytes) {
+                a.value = b;
+            }
+            // Three bytes always cover an instruction.
+            (bytes, disasm::decode(self.variant, &bytes).unwrap())
+        } else {
+            let [lo, hi] = fetched.operand.unwrap_or(0).to_le_bytes();
+            ([fetched.opcode, lo, hi], fetched)
+        };
+        for a in accesses[..count].iter_mut().filter(|a| a.status.is_ior() || a.status.is_iow()) {
+            a.value = ports.value;
+        }
+        let record = TraceRecord {
+            pc,
+            bytes,
+            instruction,
+            before,
+            after: self.regs,
+            accesses,
+            count,
+            cycles,
+            total_cycles,
+        };
+        tracer.trace(&record);
+        cycles
+    }
+}
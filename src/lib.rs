@@ -1,3 +1,5 @@
 #![cfg_attr(not(feature = "std"), no_std)]
-#![no_main]
+#![cfg_attr(not(test), no_main)]
+#[cfg(feature = "alloc")]
+extern crate alloc;
 pub mod cpu;
@@ -0,0 +1,253 @@
+//! Every ALU opcode against a straightforward reference model of the 8080
+//! datasheet rules.
+
+use i8080_core::cpu::io::Ports;
+use i8080_core::cpu::CPU;
+
+const S: u8 = 0x80;
+const Z: u8 = 0x40;
+const AC: u8 = 0x10;
+const P: u8 = 0x04;
+const CY: u8 = 0x01;
+
+/// Operands picked to hit nibble and sign boundaries.
+const SAMPLES: [u8; 16] = [
+    0x00, 0x01, 0x07, 0x08, 0x0F, 0x10, 0x3C, 0x5A, 0x7F, 0x80, 0x81, 0x99, 0xA5, 0xF0, 0xFE, 0xFF,
+];
+
+struct Machine {
+    cpu: CPU,
+    mem: [u8; 0x10000],
+}
+
+impl Machine {
+    fn new() -> Machine {
+        Machine { cpu: CPU::new(None, None), mem: [0; 0x10000] }
+    }
+
+    fn run(&mut self, program: &[u8]) {
+        self.mem[0x0100..0x0100 + program.len()].copy_from_slice(program);
+        self.cpu.regs.pc = 0x0100;
+        self.cpu.try_step(&mut self.mem, &mut Ports::new()).unwrap();
+    }
+
+    fn flags(&self) -> u8 {
+        self.cpu.regs.f.get() & (S | Z | AC | P | CY)
+    }
+}
+
+fn szp(r: u8) -> u8 {
+    let mut f = 0;
+    if r & 0x80 != 0 {
+        f |= S;
+    }
+    if r == 0 {
+        f |= Z;
+    }
+    if r.count_ones() & 1 == 0 {
+        f |= P;
+    }
+    f
+}
+
+/// Reference result and flags for ALU operation `op` (bits 5..3 of the opcode).
+fn model(op: u8, a: u8, b: u8, carry: bool) -> (u8, u8) {
+    let c = carry as i16;
+    let (a16, b16) = (a as i16, b as i16);
+    let (lo_a, lo_b) = ((a & 0x0F) as i16, (b & 0x0F) as i16);
+    let (r, cy, ac) = match op {
+        0 => (a16 + b16, a16 + b16 > 0xFF, lo_a + lo_b > 0x0F),
+        1 => (a16 + b16 + c, a16 + b16 + c > 0xFF, lo_a + lo_b + c > 0x0F),
+        2 | 7 => (a16 - b16, a16 < b16, lo_a >= lo_b),
+        3 => (a16 - b16 - c, a16 - b16 - c < 0, lo_a - lo_b - c >= 0),
+        4 => ((a & b) as i16, false, (a | b) & 0x08 != 0),
+        5 => ((a ^ b) as i16, false, false),
+        _ => ((a | b) as i16, false, false),
+    };
+    let r = r as u8;
+    let f = szp(r) | if cy { CY } else { 0 } | if ac { AC } else { 0 };
+    // CMP leaves A alone.
+    (if op == 7 { a } else { r }, f)
+}
+
+#[test]
+fn register_alu_ops() {
+    let mut m = Machine::new();
+    for opcode in 0x80..=0xBFu8 {
+        let (op, src) = ((opcode >> 3) & 7, opcode & 7);
+        for a in 0..=0xFFu8 {
+            for b in SAMPLES {
+                for carry in [false, true] {
+                    let r = &mut m.cpu.regs;
+                    (r.b, r.c, r.d, r.e, r.h, r.l) = (b, b, b, b, b, b);
+                    if src == 6 {
+                        (r.h, r.l) = (0x20, 0x00);
+                    }
+                    r.a = a;
+                    r.f.set_carry(carry);
+                    m.mem[0x2000] = b;
+                    m.run(&[opcode]);
+                    let b = if src == 7 { a } else { b };
+                    let (ra, rf) = model(op, a, b, carry);
+                    assert_eq!(
+                        (m.cpu.regs.a, m.flags()),
+                        (ra, rf),
+                        "opcode {:02X}, A={:02X}, operand={:02X}, CY={}",
+                        opcode, a, b, carry
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn immediate_alu_ops() {
+    let mut m = Machine::new();
+    for opcode in [0xC6, 0xCE, 0xD6, 0xDE, 0xE6, 0xEE, 0xF6, 0xFEu8] {
+        let op = (opcode >> 3) & 7;
+        for a in 0..=0xFFu8 {
+            for b in 0..=0xFFu8 {
+                for carry in [false, true] {
+                    m.cpu.regs.a = a;
+                    m.cpu.regs.f.set_carry(carry);
+                    m.run(&[opcode, b]);
+                    assert_eq!(
+                        (m.cpu.regs.a, m.flags()),
+                        model(op, a, b, carry),
+                        "opcode {:02X}, A={:02X}, operand={:02X}, CY={}",
+                        opcode, a, b, carry
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn inr_dcr() {
+    let mut m = Machine::new();
+    for opcode in (0x04..=0x3Du8).filter(|op| op & 0x06 == 0x04) {
+        let dst = (opcode >> 3) & 7;
+        let dec = opcode & 1 == 1;
+        for v in 0..=0xFFu8 {
+            for carry in [false, true] {
+                let r = &mut m.cpu.regs;
+                (r.a, r.b, r.c, r.d, r.e, r.h, r.l) = (v, v, v, v, v, v, v);
+                if dst == 6 {
+                    (r.h, r.l) = (0x20, 0x00);
+                }
+                r.f.set_carry(carry);
+                m.mem[0x2000] = v;
+                m.run(&[opcode]);
+                let r = &m.cpu.regs;
+                let got = [r.b, r.c, r.d, r.e, r.h, r.l, m.mem[0x2000], r.a][dst as usize];
+                let (want, ac) = if dec {
+                    (v.wrapping_sub(1), v & 0x0F != 0)
+                } else {
+                    (v.wrapping_add(1), v & 0x0F == 0x0F)
+                };
+                let f = szp(want) | if ac { AC } else { 0 } | if carry { CY } else { 0 };
+                assert_eq!((got, m.flags()), (want, f), "opcode {:02X}, value={:02X}", opcode, v);
+            }
+        }
+    }
+}
+
+#[test]
+fn daa() {
+    let mut m = Machine::new();
+    for a in 0..=0xFFu8 {
+        for (carry, aux) in [(false, false), (false, true), (true, false), (true, true)] {
+            m.cpu.regs.a = a;
+            m.cpu.regs.f.set_carry(carry);
+            m.cpu.regs.f.set_aux(aux);
+            m.run(&[0x27]);
+            // The datasheet's two steps; the upper digit is judged on the
+            // unadjusted byte, which also catches a low-digit carry out of 9xH.
+            let (mut r, mut cy, mut ac) = (a, carry, false);
+            if r & 0x0F > 9 || aux {
+                ac = (r & 0x0F) + 6 > 0x0F;
+                r = r.wrapping_add(6);
+            }
+            if r >> 4 > 9 || cy || a > 0x99 {
+                cy = true;
+                r = r.wrapping_add(0x60);
+            }
+            let f = szp(r) | if ac { AC } else { 0 } | if cy { CY } else { 0 };
+            assert_eq!(
+                (m.cpu.regs.a, m.flags()),
+                (r, f),
+                "A={:02X}, CY={}, AC={}",
+                a, carry, aux
+            );
+        }
+    }
+}
+
+#[test]
+fn rotates() {
+    let mut m = Machine::new();
+    for a in 0..=0xFFu8 {
+        for carry in [false, true] {
+            let c = carry as u8;
+            let cases = [
+                (0x07, a.rotate_left(1), a & 0x80 != 0),
+                (0x0F, a.rotate_right(1), a & 0x01 != 0),
+                (0x17, a << 1 | c, a & 0x80 != 0),
+                (0x1F, a >> 1 | c << 7, a & 0x01 != 0),
+            ];
+            for (opcode, want, cy) in cases {
+                m.cpu.regs.f.set(0x02 | S | Z | AC | P);
+                m.cpu.regs.f.set_carry(carry);
+                m.cpu.regs.a = a;
+                m.run(&[opcode]);
+                let f = S | Z | AC | P | if cy { CY } else { 0 };
+                assert_eq!((m.cpu.regs.a, m.flags()), (want, f), "opcode {:02X}, A={:02X}", opcode, a);
+            }
+        }
+    }
+}
+
+#[test]
+fn cma_stc_cmc() {
+    let mut m = Machine::new();
+    m.cpu.regs.a = 0x5A;
+    m.cpu.regs.f.set(0x02);
+    m.run(&[0x2F]);
+    assert_eq!((m.cpu.regs.a, m.flags()), (0xA5, 0));
+    m.run(&[0x37]);
+    assert_eq!(m.flags(), CY);
+    m.run(&[0x3F]);
+    assert_eq!(m.flags(), 0);
+    m.run(&[0x3F]);
+    assert_eq!(m.flags(), CY);
+}
+
+#[test]
+fn dad() {
+    let mut m = Machine::new();
+    let values = [0x0000u16, 0x0001, 0x7FFF, 0x8000, 0xFFFF, 0x1234];
+    for (opcode, rp) in [(0x09u8, 0), (0x19, 1), (0x29, 2), (0x39, 3)] {
+        for hl in values {
+            for v in values {
+                let r = &mut m.cpu.regs;
+                r.f.set(0x02 | S | Z | AC | P);
+                (r.h, r.l) = ((hl >> 8) as u8, hl as u8);
+                match rp {
+                    0 => (r.b, r.c) = ((v >> 8) as u8, v as u8),
+                    1 => (r.d, r.e) = ((v >> 8) as u8, v as u8),
+                    3 => r.sp = v,
+                    _ => {}
+                }
+                m.run(&[opcode]);
+                let v = if rp == 2 { hl } else { v };
+                let (want, cy) = hl.overflowing_add(v);
+                let r = &m.cpu.regs;
+                let got = (r.h as u16) << 8 | r.l as u16;
+                let f = S | Z | AC | P | if cy { CY } else { 0 };
+                assert_eq!((got, m.flags()), (want, f), "opcode {:02X}, HL={:04X}, rp={:04X}", opcode, hl, v);
+            }
+        }
+    }
+}
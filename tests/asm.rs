@@ -0,0 +1,153 @@
+#![cfg(feature = "asm")]
+use i8080_core::cpu::asm::{assemble, ErrorKind};
+use i8080_core::cpu::disasm::decode;
+use i8080_core::cpu::io::Ports;
+use i8080_core::cpu::{Variant, CPU};
+
+fn bytes(src: &str) -> Vec<u8> {
+    assemble(Variant::I8080, src).unwrap().image
+}
+
+fn error(src: &str) -> (usize, ErrorKind) {
+    let e = assemble(Variant::I8080, src).unwrap_err();
+    (e.line, e.kind)
+}
+
+#[test]
+fn instructions() {
+    assert_eq!(bytes("  MOV A,M"), [0x7E]);
+    assert_eq!(bytes("  lxi h, 1234h"), [0x21, 0x34, 0x12]);
+    assert_eq!(bytes("  PUSH PSW\n  POP B"), [0xF5, 0xC1]);
+    assert_eq!(bytes("  RST 7\n  RST 1+1"), [0xFF, 0xD7]);
+    assert_eq!(bytes("  MVI M,-1"), [0x36, 0xFF]);
+    assert_eq!(bytes("  NOP\n  JMP 0\n  CALL 0\n  RET"), [0x00, 0xC3, 0, 0, 0xCD, 0, 0, 0xC9]);
+}
+
+#[test]
+fn labels_forward_and_back() {
+    let src = "
+        ORG 100H
+START:  MVI B,COUNT
+LOOP    DCR B
+        JNZ LOOP
+        JMP DONE
+COUNT   EQU 3
+DONE:   HLT
+";
+    let a = assemble(Variant::I8080, src).unwrap();
+    assert_eq!(a.origin, 0x100);
+    assert_eq!(a.image, [0x06, 0x03, 0x05, 0xC2, 0x02, 0x01, 0xC3, 0x09, 0x01, 0x76]);
+    assert_eq!(a.symbol("start"), Some(0x100));
+    assert_eq!(a.symbol("LOOP"), Some(0x102));
+    assert_eq!(a.symbol("count"), Some(3));
+    assert_eq!(a.symbol("DONE"), Some(0x109));
+}
+
+#[test]
+fn data_directives() {
+    let src = "
+        ORG 10H
+        DB 1, 'AB', 'it''s', 'A'+80H, 0FFH ; comment; with ';' inside quotes
+        DW 1234H, $
+        DS 2
+        DB LOW 1234H, HIGH(1234H)
+";
+    assert_eq!(
+        bytes(src),
+        [1, b'A', b'B', b'i', b't', b'\'', b's', 0xC1, 0xFF, 0x34, 0x12, 0x19, 0x00, 0, 0, 0x34, 0x12]
+    );
+}
+
+#[test]
+fn expressions() {
+    let src = "
+A1      EQU 2 + 3 * 4          ; 14
+A2      EQU (2 + 3) * 4        ; 20
+A3      EQU 1 SHL 4 OR 1       ; 17
+A4      EQU 0x10 | 101B ^ 17Q  ; 10H | (5 ^ 15)
+A5      EQU NOT 0 AND 0FFH     ; 255
+A6      EQU 100 MOD 7 + 7 / 2  ; 5
+";
+    let a = assemble(Variant::I8080, src).unwrap();
+    let values: Vec<u16> = ["A1", "A2", "A3", "A4", "A5", "A6"].iter().map(|s| a.symbol(s).unwrap()).collect();
+    assert_eq!(values, [14, 20, 17, 0x1A, 0xFF, 5]);
+    assert!(a.image.is_empty());
+}
+
+#[test]
+fn org_gaps_are_zero_filled() {
+    let a = assemble(Variant::I8080, "  ORG 2\n  NOP\n  ORG 5\n  HLT\n  END\n  NOP").unwrap();
+    assert_eq!(a.origin, 2);
+    assert_eq!(a.image, [0x00, 0, 0, 0x76]);
+}
+
+#[test]
+fn listing() {
+    let a = assemble(Variant::I8080, "  ORG 100H\nX: LXI H,1234H\n; note").unwrap();
+    let lines: Vec<String> = a.listing.iter().map(|l| l.to_string()).collect();
+    assert_eq!(lines[1], "0100  21 34 12  X: LXI H,1234H");
+    assert_eq!(lines[2], "0103            ; note");
+    assert_eq!(a.listing[1].number, 2);
+}
+
+#[test]
+fn errors() {
+    assert_eq!(error("  NOP\n  FOO A"), (2, ErrorKind::UnknownMnemonic("FOO".into())));
+    assert_eq!(error("  MOV A"), (1, ErrorKind::InvalidOperands("A".into())));
+    assert_eq!(error("  LDAX H"), (1, ErrorKind::InvalidOperands("H".into())));
+    assert_eq!(error("  JMP NOWHERE"), (1, ErrorKind::UndefinedSymbol("NOWHERE".into())));
+    assert_eq!(error("X: NOP\nX: NOP"), (2, ErrorKind::DuplicateSymbol("X".into())));
+    assert_eq!(error("  MVI A,256"), (1, ErrorKind::OutOfRange(256)));
+    assert_eq!(error("  EQU 1"), (1, ErrorKind::MissingName));
+    assert_eq!(error("  DB 1+"), (1, ErrorKind::Syntax("end of expression".into())));
+    assert_eq!(error("  ORG 0FFFFH\n  JMP 0"), (2, ErrorKind::OutOfRange(0x10002)));
+}
+
+#[test]
+fn disassembly_round_trips() {
+    for op in 0..=0xFFu8 {
+        let i = decode(Variant::I8080, &[op, 0x34, 0x12]).unwrap();
+        let image = bytes(&format!("  {}", i));
+        let same = decode(Variant::I8080, &image).unwrap();
+        // Undocumented aliases assemble to their documented opcode.
+        assert_eq!(same.to_string(), i.to_string(), "{:02X}", op);
+        assert_eq!(image.len(), i.len as usize);
+    }
+}
+
+#[test]
+fn runs() {
+    let a = assemble(
+        Variant::I8080,
+        "
+        ORG 0
+        LXI SP,STACK
+        MVI A,0
+        MVI B,5
+LOOP:   ADD B
+        DCR B
+        JNZ LOOP
+        STA RESULT
+        HLT
+RESULT: DS 1
+        DS 16
+STACK:
+",
+    )
+    .unwrap();
+    let mut mem = [0u8; 0x10000];
+    a.load(&mut mem);
+    let mut cpu = CPU::new(None, None);
+    while !cpu.is_halted() {
+        cpu.next(&mut mem, &mut Ports::new());
+    }
+    assert_eq!(mem[a.symbol("RESULT").unwrap() as usize], 15);
+}
+
+#[cfg(feature = "i8085")]
+#[test]
+fn i8085_mnemonics() {
+    let a = assemble(Variant::I8085, "  RIM\n  JNK 1234H\n  LDHI 10H").unwrap();
+    assert_eq!(a.image, [0x20, 0xDD, 0x34, 0x12, 0x28, 0x10]);
+    assert_eq!(error("  RIM"), (1, ErrorKind::UnknownMnemonic("RIM".into())));
+}
@@ -0,0 +1,88 @@
+use i8080_core::cpu::bus::{Bus, Indexed};
+use i8080_core::cpu::io::Ports;
+use i8080_core::cpu::CPU;
+use std::ops::{Index, IndexMut};
+
+// MVI A,5AH; STA 1234H; LDA 0FFF0H; HLT
+const PROGRAM: [u8; 9] = [0x3E, 0x5A, 0x32, 0x34, 0x12, 0x3A, 0xF0, 0xFF, 0x76];
+
+fn run<M: Bus>(mem: &mut M) -> CPU {
+    let mut cpu = CPU::new(None, None);
+    while !cpu.is_halted() {
+        cpu.next(mem, &mut Ports::new());
+    }
+    cpu
+}
+
+#[test]
+fn array_bus() {
+    let mut mem = [0u8; 0x10000];
+    mem[..PROGRAM.len()].copy_from_slice(&PROGRAM);
+    mem[0xFFF0] = 0x77;
+    let cpu = run(&mut mem);
+    assert_eq!((mem[0x1234], cpu.regs.a), (0x5A, 0x77));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn short_vec_reads_ff_and_drops_writes() {
+    let mut mem = PROGRAM.to_vec();
+    mem.resize(0x100, 0);
+    let cpu = run(&mut mem);
+    assert_eq!(mem.len(), 0x100);
+    assert_eq!(cpu.regs.a, 0xFF);
+    assert_eq!(mem.read(0x1234), 0xFF);
+}
+
+/// Memory behind an `IndexMut` impl, as the core took it before `Bus`.
+struct Paged(Vec<u8>);
+impl Index<u16> for Paged {
+    type Output = u8;
+    fn index(&self, addr: u16) -> &u8 {
+        &self.0[addr as usize]
+    }
+}
+impl IndexMut<u16> for Paged {
+    fn index_mut(&mut self, addr: u16) -> &mut u8 {
+        &mut self.0[addr as usize]
+    }
+}
+
+#[test]
+fn indexed_adapter() {
+    let mut mem = Indexed(Paged(vec![0; 0x10000]));
+    mem.0 .0[..PROGRAM.len()].copy_from_slice(&PROGRAM);
+    mem.0 .0[0xFFF0] = 0x33;
+    let cpu = run(&mut mem);
+    assert_eq!((mem.0 .0[0x1234], cpu.regs.a), (0x5A, 0x33));
+}
+
+/// RAM with a status register at FFF0H that counts how often it is read.
+struct Mmio {
+    ram: [u8; 0x10000],
+    status_reads: u8,
+    last_write: Option<(u16, u8)>,
+}
+impl Bus for Mmio {
+    fn read(&mut self, addr: u16) -> u8 {
+        if addr == 0xFFF0 {
+            self.status_reads += 1;
+            return 0x80 | self.status_reads;
+        }
+        self.ram[addr as usize]
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        self.last_write = Some((addr, val));
+        self.ram[addr as usize] = val;
+    }
+}
+
+#[test]
+fn mapped_device() {
+    let mut mem = Mmio { ram: [0; 0x10000], status_reads: 0, last_write: None };
+    mem.ram[..PROGRAM.len()].copy_from_slice(&PROGRAM);
+    let cpu = run(&mut mem);
+    assert_eq!(cpu.regs.a, 0x81);
+    assert_eq!(mem.status_reads, 1);
+    assert_eq!(mem.last_write, Some((0x1234, 0x5A)));
+}
@@ -0,0 +1,18 @@
+//! Fixtures shared by the integration tests.
+#![allow(dead_code)]
+use i8080_core::cpu::CPU;
+
+/// Flat memory holding `program` at `origin`, with `cpu` about to run it and
+/// its stack at 8000H.
+pub fn load(mut cpu: CPU, origin: u16, program: &[u8]) -> (CPU, [u8; 0x10000]) {
+    let mut mem = [0u8; 0x10000];
+    mem[origin as usize..origin as usize + program.len()].copy_from_slice(program);
+    cpu.regs.pc = origin;
+    cpu.regs.sp = 0x8000;
+    (cpu, mem)
+}
+
+/// An 8080 about to run `program` at 0100H.
+pub fn machine(program: &[u8]) -> (CPU, [u8; 0x10000]) {
+    load(CPU::new(None, None), 0x0100, program)
+}
@@ -0,0 +1,231 @@
+use i8080_core::cpu::bus::{Bus, MachineCycle, Status};
+use i8080_core::cpu::io::Ports;
+use i8080_core::cpu::CPU;
+
+/// Flat memory that logs every machine cycle the CPU reports.
+struct Recorder {
+    mem: [u8; 0x10000],
+    log: Vec<(u32, Status, u16)>,
+}
+
+impl Bus for Recorder {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.mem[addr as usize]
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        self.mem[addr as usize] = val;
+    }
+    fn cycle(&mut self, c: MachineCycle) -> u8 {
+        self.log.push((c.t_state, c.status, c.addr));
+        0
+    }
+}
+
+fn run(program: &[u8], setup: impl FnOnce(&mut CPU)) -> (CPU, Vec<(u32, Status, u16)>, u32) {
+    let mut bus = Recorder { mem: [0; 0x10000], log: Vec::new() };
+    bus.mem[0x0100..0x0100 + program.len()].copy_from_slice(program);
+    let mut cpu = CPU::new(None, None);
+    cpu.regs.pc = 0x0100;
+    cpu.regs.sp = 0x8000;
+    setup(&mut cpu);
+    let cycles = cpu.next(&mut bus, &mut Ports::new());
+    (cpu, bus.log, cycles)
+}
+
+#[test]
+fn call_has_long_m1_then_operand_and_stack_cycles() {
+    let (_, log, cycles) = run(&[0xCD, 0x34, 0x12], |_| {});
+    assert_eq!(cycles, 17);
+    assert_eq!(
+        log,
+        [
+            (0, Status::FETCH, 0x0100),
+            (5, Status::MEMORY_READ, 0x0101),
+            (8, Status::MEMORY_READ, 0x0102),
+            (11, Status::STACK_WRITE, 0x7FFF),
+            (14, Status::STACK_WRITE, 0x7FFE),
+        ]
+    );
+}
+
+#[test]
+fn not_taken_jump_still_reads_operand() {
+    let (_, log, cycles) = run(&[0xCA, 0x34, 0x12], |_| {}); // JZ with Z clear
+    assert_eq!(cycles, 10);
+    assert_eq!(
+        log,
+        [
+            (0, Status::FETCH, 0x0100),
+            (4, Status::MEMORY_READ, 0x0101),
+            (7, Status::MEMORY_READ, 0x0102),
+        ]
+    );
+}
+
+#[test]
+fn mov_m_and_inr_m() {
+    let (_, log, _) = run(&[0x70], |c| (c.regs.h, c.regs.l) = (0x20, 0x00)); // MOV M,B
+    assert_eq!(log, [(0, Status::FETCH, 0x0100), (4, Status::MEMORY_WRITE, 0x2000)]);
+    let (_, log, _) = run(&[0x34], |c| (c.regs.h, c.regs.l) = (0x20, 0x00)); // INR M
+    assert_eq!(
+        log,
+        [
+            (0, Status::FETCH, 0x0100),
+            (4, Status::MEMORY_READ, 0x2000),
+            (7, Status::MEMORY_WRITE, 0x2000),
+        ]
+    );
+}
+
+#[test]
+fn io_cycles_carry_port_on_both_address_halves() {
+    let (_, log, _) = run(&[0xDB, 0x42], |_| {});
+    assert_eq!(log[2], (7, Status::INPUT_READ, 0x4242));
+    assert!(log[2].1.is_ior());
+    let (_, log, _) = run(&[0xD3, 0x42], |_| {});
+    assert_eq!(log[2], (7, Status::OUTPUT_WRITE, 0x4242));
+    assert!(log[2].1.is_iow() && !log[2].1.is_memw());
+}
+
+#[test]
+fn xthl_order() {
+    let (_, log, cycles) = run(&[0xE3], |_| {});
+    assert_eq!(cycles, 18);
+    assert_eq!(
+        log,
+        [
+            (0, Status::FETCH, 0x0100),
+            (4, Status::STACK_READ, 0x8000),
+            (7, Status::STACK_READ, 0x8001),
+            (10, Status::STACK_WRITE, 0x8001),
+            (13, Status::STACK_WRITE, 0x8000),
+        ]
+    );
+}
+
+#[test]
+fn hlt_reports_halt_acknowledge() {
+    let (_, log, _) = run(&[0x76], |_| {});
+    assert_eq!(log, [(0, Status::FETCH, 0x0100), (4, Status::HALT_ACK, 0x0101)]);
+    assert!(log[1].1.is_hlta());
+}
+
+#[test]
+fn interrupt_acknowledge_replaces_fetch() {
+    let (_, log, _) = run(&[0x00], |c| {
+        c.interrupt_enabled = true;
+        c.request_interrupt(&[0xFF]);
+    });
+    assert_eq!(
+        log,
+        [
+            (0, Status::INTERRUPT_ACK, 0x0100),
+            (5, Status::STACK_WRITE, 0x7FFF),
+            (8, Status::STACK_WRITE, 0x7FFE),
+        ]
+    );
+    assert!(log[0].1.is_inta() && log[0].1.is_m1());
+
+    // The address bytes of an injected CALL come from the device as well.
+    let (_, log, _) = run(&[0x00], |c| {
+        c.interrupt_enabled = true;
+        c.request_interrupt(&[0xCD, 0x00, 0x20]);
+    });
+    let inta = Status(Status::INTA | Status::WO);
+    assert_eq!(log[1].1, inta);
+    assert_eq!(log[2].1, inta);
+    assert_eq!(log[3], (11, Status::STACK_WRITE, 0x7FFF));
+}
+
+/// ROM below 2000H needs one wait state per read; port 10H needs two.
+struct SlowRom {
+    mem: [u8; 0x10000],
+    log: Vec<(u32, Status, u16)>,
+}
+
+impl Bus for SlowRom {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.mem[addr as usize]
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        self.mem[addr as usize] = val;
+    }
+    fn cycle(&mut self, c: MachineCycle) -> u8 {
+        self.log.push((c.t_state, c.status, c.addr));
+        (c.status.is_memr() && c.addr < 0x2000) as u8
+    }
+}
+
+struct SlowPort;
+
+impl i8080_core::cpu::io::IoBus for SlowPort {
+    fn read_port(&mut self, _port: u8) -> u8 {
+        0
+    }
+    fn write_port(&mut self, _port: u8, _value: u8) {}
+    fn cycle(&mut self, c: MachineCycle) -> u8 {
+        if c.addr & 0xFF == 0x10 {
+            2
+        } else {
+            0
+        }
+    }
+}
+
+#[test]
+fn wait_states_stretch_cycles_and_shift_offsets() {
+    let mut bus = SlowRom { mem: [0; 0x10000], log: Vec::new() };
+    // LDA 3000H: three reads from ROM, one from RAM.
+    bus.mem[..3].copy_from_slice(&[0x3A, 0x00, 0x30]);
+    let mut cpu = CPU::new(None, None);
+    assert_eq!(cpu.next(&mut bus, &mut SlowPort), 13 + 3);
+    assert_eq!(
+        bus.log,
+        [
+            (0, Status::FETCH, 0x0000),
+            (5, Status::MEMORY_READ, 0x0001),
+            (9, Status::MEMORY_READ, 0x0002),
+            (13, Status::MEMORY_READ, 0x3000),
+        ]
+    );
+    assert_eq!(cpu.cycles(), 16);
+}
+
+#[test]
+fn io_wait_states() {
+    let mut bus = SlowRom { mem: [0; 0x10000], log: Vec::new() };
+    bus.mem[0x2000..0x2004].copy_from_slice(&[0xD3, 0x10, 0xD3, 0x11]); // OUT 10H; OUT 11H
+    let mut cpu = CPU::new(None, None);
+    cpu.regs.pc = 0x2000;
+    assert_eq!(cpu.next(&mut bus, &mut SlowPort), 12);
+    assert_eq!(cpu.next(&mut bus, &mut SlowPort), 10);
+}
+
+/// Holds every machine cycle for 100 wait states.
+struct Sluggish {
+    mem: [u8; 0x10000],
+    log: Vec<u32>,
+}
+
+impl Bus for Sluggish {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.mem[addr as usize]
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        self.mem[addr as usize] = val;
+    }
+    fn cycle(&mut self, c: MachineCycle) -> u8 {
+        self.log.push(c.t_state);
+        100
+    }
+}
+
+#[test]
+fn wait_states_past_255_are_counted() {
+    let mut bus = Sluggish { mem: [0; 0x10000], log: Vec::new() };
+    bus.mem[..3].copy_from_slice(&[0xCD, 0x00, 0x20]); // CALL 2000H
+    let mut cpu = CPU::new(None, Some(0x8000));
+    assert_eq!(cpu.next(&mut bus, &mut Ports::new()), 17 + 500);
+    assert_eq!(bus.log, [0, 105, 208, 311, 414]);
+    assert_eq!(cpu.cycles(), 517);
+}
@@ -0,0 +1,91 @@
+use i8080_core::cpu::disasm::{decode, decode_at, Syntax};
+use i8080_core::cpu::Variant;
+
+fn intel(bytes: &[u8]) -> String {
+    decode(Variant::I8080, bytes).unwrap().to_string()
+}
+
+fn zilog(bytes: &[u8]) -> String {
+    decode(Variant::I8080, bytes).unwrap().display(Syntax::Zilog).to_string()
+}
+
+#[test]
+fn intel_mnemonics() {
+    assert_eq!(intel(&[0x7E]), "MOV A,M");
+    assert_eq!(intel(&[0x21, 0x34, 0x12]), "LXI H,1234H");
+    assert_eq!(intel(&[0xF5]), "PUSH PSW");
+    assert_eq!(intel(&[0x31, 0x00, 0xF0]), "LXI SP,0F000H");
+    assert_eq!(intel(&[0xFE, 0xFF]), "CPI 0FFH");
+    assert_eq!(intel(&[0xC2, 0x00, 0x01]), "JNZ 0100H");
+    assert_eq!(intel(&[0xF8]), "RM");
+    assert_eq!(intel(&[0xFF]), "RST 7");
+    assert_eq!(intel(&[0xDB, 0x10]), "IN 10H");
+    assert_eq!(intel(&[0x76]), "HLT");
+    assert_eq!(intel(&[0x0A]), "LDAX B");
+}
+
+#[test]
+fn zilog_mnemonics() {
+    assert_eq!(zilog(&[0x7E]), "LD A,(HL)");
+    assert_eq!(zilog(&[0x21, 0x34, 0x12]), "LD HL,1234H");
+    assert_eq!(zilog(&[0xF1]), "POP AF");
+    assert_eq!(zilog(&[0xCC, 0x00, 0x20]), "CALL Z,2000H");
+    assert_eq!(zilog(&[0xC0]), "RET NZ");
+    assert_eq!(zilog(&[0xEF]), "RST 28H");
+    assert_eq!(zilog(&[0xD3, 0xFE]), "OUT (0FEH),A");
+    assert_eq!(zilog(&[0x1A]), "LD A,(DE)");
+}
+
+#[test]
+fn fields() {
+    let i = decode(Variant::I8080, &[0xC4, 0x34, 0x12]).unwrap();
+    assert_eq!((i.opcode, i.operand, i.len), (0xC4, Some(0x1234), 3));
+    assert_eq!(i.mnemonic(Syntax::Intel), "CNZ");
+    assert_eq!(i.mnemonic(Syntax::Zilog), "CALL");
+    assert_eq!((i.cycles, i.cycles_not_taken), (17, Some(11)));
+
+    let i = decode(Variant::I8080, &[0xC8]).unwrap();
+    assert_eq!((i.cycles, i.cycles_not_taken), (11, Some(5)));
+    let i = decode(Variant::I8080, &[0x7E]).unwrap();
+    assert_eq!((i.operand, i.len, i.cycles, i.cycles_not_taken), (None, 1, 7, None));
+    let i = decode(Variant::I8080, &[0x06, 0x42]).unwrap();
+    assert_eq!((i.operand, i.len), (Some(0x42), 2));
+}
+
+#[test]
+fn short_slice() {
+    assert_eq!(decode(Variant::I8080, &[]), None);
+    assert_eq!(decode(Variant::I8080, &[0xC3, 0x00]), None);
+}
+
+#[test]
+fn every_opcode_decodes() {
+    for op in 0..=0xFFu8 {
+        let i = decode(Variant::I8080, &[op, 0, 0]).unwrap();
+        assert!(!intel(&[op, 0, 0]).starts_with("DB"), "{:02X}", op);
+        assert!(i.cycles >= 4, "{:02X}", op);
+    }
+}
+
+#[test]
+fn from_bus() {
+    let mut mem = [0u8; 0x10000];
+    mem[0xFFFF] = 0x3A; // LDA wraps around for its operand
+    mem[0x0000] = 0x00;
+    mem[0x0001] = 0x30;
+    let i = decode_at(Variant::I8080, &mut mem, 0xFFFF);
+    assert_eq!(i.to_string(), "LDA 3000H");
+    assert_eq!(i.cycles, 13);
+}
+
+#[cfg(feature = "i8085")]
+#[test]
+fn i8085_mnemonics() {
+    let i = decode(Variant::I8085, &[0xDD, 0x00, 0x20]).unwrap();
+    assert_eq!(i.to_string(), "JNK 2000H");
+    assert_eq!((i.cycles, i.cycles_not_taken), (10, Some(7)));
+    assert_eq!(decode(Variant::I8085, &[0x28, 0x10]).unwrap().to_string(), "LDHI 10H");
+    assert_eq!(decode(Variant::I8085, &[0x20]).unwrap().to_string(), "RIM");
+    // The same byte is an undocumented CALL on the 8080.
+    assert_eq!(intel(&[0xDD, 0x00, 0x20]), "CALL 2000H");
+}
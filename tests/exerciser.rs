@@ -0,0 +1,145 @@
+//! Runs the classic CP/M 8080 test programs (TST8080, 8080PRE, CPUTEST,
+//! 8080EXM, 8080EXER) under a tiny BDOS shim.
+//!
+//! The binaries are not redistributed with the crate, so every test that needs
+//! one is ignored by default. Drop the `.COM` files into `tests/roms/` and run
+//! `cargo test --release --test exerciser -- --ignored`; a missing file then
+//! fails the test instead of passing it silently.
+
+use std::path::PathBuf;
+
+use i8080_core::cpu::io::Ports;
+use i8080_core::cpu::CPU;
+
+/// Hard stop so a broken core cannot hang the test run.
+const CYCLE_LIMIT: u64 = 50_000_000_000;
+
+/// Loads `program` at 0100H as a CP/M transient and runs it until it jumps to
+/// the warm boot vector. Returns everything printed through BDOS 2 and 9.
+fn run_cpm(program: &[u8]) -> String {
+    let mut mem = [0u8; 0x10000];
+    mem[0x0100..0x0100 + program.len()].copy_from_slice(program);
+    // Warm boot halts the CPU; BDOS entry is a bare RET whose operand bytes
+    // still tell programs where the top of the TPA is.
+    mem[0x0000] = 0x76; // HLT
+    mem[0x0005] = 0xC9; // RET
+    mem[0x0006] = 0x00;
+    mem[0x0007] = 0xF0;
+
+    let mut io = Ports::new();
+    let mut cpu = CPU::new(None, None);
+    cpu.regs.pc = 0x0100;
+    cpu.regs.sp = 0xF000;
+
+    let mut out = String::new();
+    let mut cycles = 0u64;
+    while !cpu.is_halted() {
+        if cpu.regs.pc == 0x0005 {
+            bdos(&cpu, &mem, &mut out);
+        }
+        cycles += cpu.next(&mut mem, &mut io) as u64;
+        assert!(cycles < CYCLE_LIMIT, "no warm boot after {cycles} cycles:\n{out}");
+    }
+    out
+}
+
+fn bdos(cpu: &CPU, mem: &[u8; 0x10000], out: &mut String) {
+    match cpu.regs.c {
+        2 => out.push(cpu.regs.e as char),
+        9 => {
+            let mut addr = (cpu.regs.d as u16) << 8 | cpu.regs.e as u16;
+            while mem[addr as usize] != b'$' {
+                out.push(mem[addr as usize] as char);
+                addr = addr.wrapping_add(1);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn rom(name: &str) -> Vec<u8> {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "roms", name].iter().collect();
+    std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {e}; see tests/roms/README.md", path.display()))
+}
+
+fn assert_passes(name: &str, expect: &str) -> String {
+    let out = run_cpm(&rom(name));
+    println!("{out}");
+    assert!(!out.contains("ERROR"), "{name} reported a failure:\n{out}");
+    assert!(out.contains(expect), "{name} did not finish:\n{out}");
+    out
+}
+
+/// The exercisers print one `name....  PASS! crc is:xxxxxxxx` line per test, or
+/// an `ERROR **** crc expected:... found:...` line when the CRC differs.
+fn assert_crcs(name: &str, out: &str) {
+    let results: Vec<&str> = out.lines().filter(|l| l.contains("crc")).collect();
+    assert_eq!(results.len(), EXERCISER_TESTS, "{name} ran the wrong number of tests:\n{out}");
+    for line in results {
+        let crc = line.split("PASS! crc is:").nth(1).map(str::trim);
+        assert!(
+            crc.is_some_and(|c| c.len() == 8 && c.chars().all(|c| c.is_ascii_hexdigit())),
+            "{name}: {line}"
+        );
+    }
+}
+
+/// Instruction groups covered by 8080EXM and 8080EXER.
+const EXERCISER_TESTS: usize = 25;
+
+#[test]
+fn bdos_shim_prints() {
+    let program = [
+        0x0E, 0x02,       // MVI C,2
+        0x1E, b'>',       // MVI E,'>'
+        0xCD, 0x05, 0x00, // CALL 5
+        0x11, 0x12, 0x01, // LXI D,MSG
+        0x0E, 0x09,       // MVI C,9
+        0xCD, 0x05, 0x00, // CALL 5
+        0xC3, 0x00, 0x00, // JMP 0
+        b'O', b'K', b'$', // MSG
+    ];
+    assert_eq!(run_cpm(&program), ">OK");
+}
+
+#[test]
+#[ignore = "needs tests/roms/TST8080.COM"]
+fn tst8080() {
+    assert_passes("TST8080.COM", "CPU IS OPERATIONAL");
+}
+
+#[test]
+#[ignore = "needs tests/roms/CPUTEST.COM"]
+fn cputest() {
+    assert_passes("CPUTEST.COM", "CPU TESTS OK");
+}
+
+#[test]
+#[ignore = "needs tests/roms/8080PRE.COM"]
+fn i8080pre() {
+    assert_passes("8080PRE.COM", "Preliminary tests complete");
+}
+
+#[test]
+#[ignore = "needs tests/roms/8080EXM.COM; takes minutes unless run with --release"]
+fn i8080exm() {
+    let out = assert_passes("8080EXM.COM", "Tests complete");
+    assert_crcs("8080EXM.COM", &out);
+}
+
+#[test]
+#[ignore = "needs tests/roms/8080EXER.COM; takes minutes unless run with --release"]
+fn i8080exer() {
+    let out = assert_passes("8080EXER.COM", "Tests complete");
+    assert_crcs("8080EXER.COM", &out);
+}
+
+#[test]
+fn crc_lines_are_checked() {
+    let pass = "dad <b,d,h,sp>................  PASS! crc is:14474ba6\r\n".repeat(EXERCISER_TESTS);
+    assert_crcs("sample", &pass);
+    let fail = pass.replacen("PASS! crc is:14474ba6", "ERROR **** crc expected:14474ba6 found:00000000", 1);
+    assert!(std::panic::catch_unwind(|| assert_crcs("sample", &fail)).is_err());
+    let short = pass.replacen("PASS! crc is:14474ba6", "", 1);
+    assert!(std::panic::catch_unwind(|| assert_crcs("sample", &short)).is_err());
+}
@@ -0,0 +1,73 @@
+use i8080_core::cpu::io::Ports;
+use i8080_core::cpu::regs::flags::{FlagBits, Flags};
+use i8080_core::cpu::CPU;
+
+#[test]
+fn set_forces_fixed_bits() {
+    let mut f = Flags::default();
+    assert_eq!(f.get(), 0x02);
+    f.set(0xFF);
+    assert_eq!(f.get(), 0xD7);
+    f.set(0x00);
+    assert_eq!(f.get(), 0x02);
+}
+
+#[test]
+fn pop_psw_cannot_corrupt_push_psw() {
+    // POP PSW; PUSH PSW
+    let mut mem = [0u8; 0x10000];
+    mem[0x0000] = 0xF1;
+    mem[0x0001] = 0xF5;
+    mem[0x8000] = 0x28; // only the always-zero bits, bit 1 clear
+    mem[0x8001] = 0x12;
+    let mut cpu = CPU::new(None, None);
+    cpu.regs.sp = 0x8000;
+    let mut io = Ports::new();
+    cpu.next(&mut mem, &mut io);
+    cpu.next(&mut mem, &mut io);
+    assert_eq!((mem[0x8000], mem[0x8001]), (0x02, 0x12));
+}
+
+#[test]
+fn named_bits_round_trip() {
+    let bits = FlagBits { sign: true, zero: false, aux: true, parity: false, carry: true };
+    let f = Flags::from(bits);
+    assert_eq!(f.get(), 0x93);
+    assert_eq!(FlagBits::from(f), bits);
+}
+
+#[cfg(feature = "i8085")]
+#[test]
+fn i8085_psw_keeps_v_and_k() {
+    use i8080_core::cpu::regs::Registers;
+    use i8080_core::cpu::Variant;
+    // POP PSW; PUSH PSW
+    let mut mem = [0u8; 0x10000];
+    mem[0x0000] = 0xF1;
+    mem[0x0001] = 0xF5;
+    mem[0x8000] = 0xFF;
+    let mut cpu = CPU::with_variant(Variant::I8085);
+    assert_eq!(cpu.psw(), 0x00);
+    cpu.regs.sp = 0x8000;
+    let mut io = Ports::new();
+    cpu.next(&mut mem, &mut io);
+    assert!(cpu.v_flag() && cpu.k_flag());
+    assert_eq!((cpu.psw(), cpu.regs.f.get()), (0xF7, 0xD7));
+    // Replacing the registers leaves K and V alone.
+    cpu.regs = Registers { pc: 0x0001, sp: 0x8002, ..Registers::default() };
+    assert_eq!(cpu.psw(), 0x22);
+    cpu.next(&mut mem, &mut io);
+    assert_eq!(mem[0x8000], 0x22);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_cannot_break_fixed_bits() {
+    let mut f = Flags::default();
+    f.set(0xC1);
+    assert_eq!(serde_json::to_string(&f).unwrap(), "195");
+    let f: Flags = serde_json::from_str("255").unwrap();
+    assert_eq!(f.get(), 0xD7);
+    let f: Flags = serde_json::from_str("0").unwrap();
+    assert_eq!(f.get(), 0x02);
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use i8080_core::cpu::io::Ports;
+use common::machine;
+
+#[test]
+fn halted_core_idles_until_interrupt() {
+    let (mut cpu, mut mem) = machine(&[0xFB, 0x76, 0x3C]); // EI; HLT; INR A
+    mem[0x38] = 0xC9; // RET
+    let mut io = Ports::new();
+    cpu.next(&mut mem, &mut io);
+    assert_eq!(cpu.next(&mut mem, &mut io), 7);
+    assert!(cpu.is_halted());
+    let cycles = cpu.cycles();
+    for _ in 0..10 {
+        assert_eq!(cpu.next(&mut mem, &mut io), 4);
+        assert_eq!(cpu.regs.pc, 0x0102);
+        assert!(cpu.is_halted());
+    }
+    assert_eq!(cpu.cycles(), cycles + 40);
+
+    cpu.request_interrupt(&[0xFF]); // RST 7
+    cpu.next(&mut mem, &mut io);
+    assert!(!cpu.is_halted());
+    assert_eq!(cpu.regs.pc, 0x0038);
+    cpu.next(&mut mem, &mut io);
+    cpu.next(&mut mem, &mut io);
+    // RET lands after HLT.
+    assert_eq!((cpu.regs.pc, cpu.regs.a), (0x0103, 1));
+}
+
+#[test]
+fn disabled_interrupt_does_not_wake() {
+    let (mut cpu, mut mem) = machine(&[0x76]); // HLT
+    let mut io = Ports::new();
+    cpu.next(&mut mem, &mut io);
+    cpu.request_interrupt(&[0xFF]);
+    for _ in 0..5 {
+        assert_eq!(cpu.next(&mut mem, &mut io), 4);
+    }
+    assert!(cpu.is_halted() && cpu.interrupt_pending());
+    assert_eq!(cpu.regs.pc, 0x0101);
+}
@@ -0,0 +1,147 @@
+#![cfg(feature = "alloc")]
+mod common;
+
+use i8080_core::cpu::bus::Bus;
+use i8080_core::cpu::history::History;
+use i8080_core::cpu::io::Ports;
+use i8080_core::cpu::CPU;
+use common::load;
+
+/// Counts B down from 5, pushing each value and storing it at 2000H.
+const PROGRAM: [u8; 12] = [
+    0x06, 0x05, //       MVI B,5
+    0xC5, //             LOOP: PUSH B
+    0x78, //             MOV A,B
+    0x32, 0x00, 0x20, // STA 2000H
+    0x05, //             DCR B
+    0xC2, 0x02, 0x00, // JNZ LOOP
+    0x76, //             HLT
+];
+
+fn machine() -> (CPU, [u8; 0x10000]) {
+    load(CPU::new(None, None), 0, &PROGRAM)
+}
+
+#[test]
+fn step_back_restores_registers_and_memory() {
+    let (mut cpu, mut mem) = machine();
+    let (start, start_mem) = (cpu, mem);
+    let mut io = Ports::new();
+    let mut history = History::new(1 << 20);
+    let mut states = vec![(cpu.regs, cpu.cycles())];
+    while !cpu.is_halted() {
+        cpu.step_recorded(&mut mem, &mut io, &mut history);
+        states.push((cpu.regs, cpu.cycles()));
+    }
+    assert_eq!(mem[0x2000], 1);
+    let steps = history.len();
+    assert_eq!(steps, states.len() - 1);
+
+    assert_eq!(history.step_back(&mut cpu, &mut mem, 3), 3);
+    let (regs, cycles) = states[steps - 3];
+    assert_eq!((cpu.regs.pc, cpu.regs.b, cpu.regs.sp, cpu.cycles()), (regs.pc, regs.b, regs.sp, cycles));
+    assert!(!cpu.is_halted());
+
+    assert_eq!(history.step_back(&mut cpu, &mut mem, 1000), steps - 3);
+    assert!(history.is_empty());
+    assert_eq!(cpu.regs.pc, start.regs.pc);
+    assert_eq!(cpu.cycles(), 0);
+    assert!(mem == start_mem);
+}
+
+#[test]
+fn rewind_to_cycle_lands_on_instruction_start() {
+    let (mut cpu, mut mem) = machine();
+    let mut io = Ports::new();
+    let mut history = History::new(1 << 20);
+    let mut starts = Vec::new();
+    while !cpu.is_halted() {
+        starts.push((cpu.cycles(), cpu.regs.pc, mem[0x2000]));
+        cpu.step_recorded(&mut mem, &mut io, &mut history);
+    }
+    // Part way into the fifth instruction.
+    let target = starts[4].0 + 2;
+    assert!(history.rewind_to_cycle(&mut cpu, &mut mem, target));
+    assert_eq!((cpu.cycles(), cpu.regs.pc, mem[0x2000]), starts[4]);
+
+    // Running forward again reproduces the original run.
+    while !cpu.is_halted() {
+        cpu.step_recorded(&mut mem, &mut io, &mut history);
+    }
+    assert_eq!((cpu.regs.b, mem[0x2000], cpu.regs.sp), (0, 1, 0x8000 - 10));
+}
+
+#[test]
+fn budget_drops_oldest_steps() {
+    let (mut cpu, mut mem) = machine();
+    let mut io = Ports::new();
+    let mut history = History::new(1 << 20);
+    cpu.step_recorded(&mut mem, &mut io, &mut history);
+    let per_step = history.bytes_used();
+    history.set_budget(per_step * 4 + 16);
+    while !cpu.is_halted() {
+        cpu.step_recorded(&mut mem, &mut io, &mut history);
+        assert!(history.bytes_used() <= history.budget());
+    }
+    assert!(history.len() >= 3 && history.len() <= 4);
+    let oldest = history.oldest_cycle().unwrap();
+    assert!(!history.rewind_to_cycle(&mut cpu, &mut mem, 0));
+    assert_eq!(cpu.cycles(), oldest);
+    assert!(history.is_empty());
+}
+
+#[test]
+fn breakpoints_survive_rewind() {
+    use i8080_core::cpu::error::CpuError;
+    let (mut cpu, mut mem) = machine();
+    let mut io = Ports::new();
+    let mut history = History::new(1 << 20);
+    for _ in 0..4 {
+        cpu.step_recorded(&mut mem, &mut io, &mut history);
+    }
+    cpu.set_breakpoint(0x0007); // DCR B
+    history.step_back(&mut cpu, &mut mem, 4);
+    let stop = loop {
+        if let Err(e) = cpu.try_step(&mut mem, &mut io) {
+            break e;
+        }
+    };
+    assert_eq!(stop, CpuError::Breakpoint { pc: 0x0007 });
+}
+
+/// RAM with a UART at 3000H whose receive FIFO pops on every read.
+struct Uart {
+    mem: [u8; 0x10000],
+    rx: Vec<u8>,
+}
+
+impl Bus for Uart {
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x3000 if !self.rx.is_empty() => self.rx.remove(0),
+            _ => self.mem[addr as usize],
+        }
+    }
+    fn peek(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x3000 => self.rx.first().copied().unwrap_or(self.mem[addr as usize]),
+            _ => self.mem[addr as usize],
+        }
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        self.mem[addr as usize] = val;
+    }
+}
+
+#[test]
+fn journal_does_not_read_mapped_devices() {
+    let mut bus = Uart { mem: [0; 0x10000], rx: vec![0x41, 0x42] };
+    bus.mem[..4].copy_from_slice(&[0x32, 0x00, 0x30, 0x76]); // STA 3000H; HLT
+    let mut cpu = CPU::new(None, Some(0x8000));
+    let mut history = History::new(1 << 20);
+    cpu.step_recorded(&mut bus, &mut Ports::new(), &mut history);
+    assert_eq!(bus.rx, [0x41, 0x42]);
+    // Undoing the write puts back what a peek saw.
+    history.step_back(&mut cpu, &mut bus, 1);
+    assert_eq!(bus.mem[0x3000], 0x41);
+}
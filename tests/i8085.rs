@@ -0,0 +1,240 @@
+#![cfg(feature = "i8085")]
+mod common;
+
+use i8080_core::cpu::bus::{Bus, MachineCycle, Status};
+use i8080_core::cpu::io::Ports;
+use i8080_core::cpu::{Variant, CPU};
+use common::load;
+
+fn machine(program: &[u8]) -> (CPU, [u8; 0x10000]) {
+    load(CPU::with_variant(Variant::I8085), 0x0100, program)
+}
+
+fn step(cpu: &mut CPU, mem: &mut [u8; 0x10000]) -> u32 {
+    cpu.next(mem, &mut Ports::new())
+}
+
+#[test]
+fn timing_differs_from_8080() {
+    // MOV B,C; INX B; PUSH B; JZ 0000H (not taken); CALL 0200H
+    let (mut cpu, mut mem) = machine(&[0x41, 0x03, 0xC5, 0xCA, 0x00, 0x00, 0xCD, 0x00, 0x02]);
+    let cycles: Vec<u32> = (0..5).map(|_| step(&mut cpu, &mut mem)).collect();
+    assert_eq!(cycles, [4, 6, 12, 7, 18]);
+}
+
+#[test]
+fn undocumented_slots_stay_nops_on_8080() {
+    let mut mem = [0u8; 0x10000];
+    mem[0] = 0x08;
+    let mut cpu = CPU::new(None, None);
+    assert_eq!(cpu.next(&mut mem, &mut Ports::new()), 4);
+    assert_eq!(cpu.regs.pc, 1);
+}
+
+#[test]
+fn sim_sets_mask_and_rim_reads_it_back() {
+    // MVI A,0EH; SIM; EI; RIM
+    let (mut cpu, mut mem) = machine(&[0x3E, 0x0E, 0x30, 0xFB, 0x20]);
+    for _ in 0..3 {
+        step(&mut cpu, &mut mem);
+    }
+    cpu.set_sid(true);
+    cpu.set_rst55(true);
+    step(&mut cpu, &mut mem);
+    assert_eq!(cpu.interrupt_mask(), 0x06);
+    assert_eq!(cpu.regs.a, 0x80 | 0x10 | 0x08 | 0x06);
+}
+
+#[test]
+fn rst55_is_vectored_when_unmasked() {
+    // MVI A,08H; SIM; EI; NOP; NOP
+    let (mut cpu, mut mem) = machine(&[0x3E, 0x08, 0x30, 0xFB, 0x00, 0x00]);
+    for _ in 0..3 {
+        step(&mut cpu, &mut mem);
+    }
+    cpu.set_rst55(true);
+    step(&mut cpu, &mut mem);
+    assert_eq!(step(&mut cpu, &mut mem), 12);
+    assert_eq!(cpu.regs.pc, 0x002C);
+    assert_eq!((mem[0x7FFF], mem[0x7FFE]), (0x01, 0x05));
+    assert!(!cpu.interrupt_enabled);
+}
+
+#[test]
+fn masked_rst65_is_ignored() {
+    // MVI A,0AH; SIM; EI; NOP; NOP
+    let (mut cpu, mut mem) = machine(&[0x3E, 0x0A, 0x30, 0xFB, 0x00, 0x00]);
+    cpu.set_rst65(true);
+    for _ in 0..5 {
+        step(&mut cpu, &mut mem);
+    }
+    assert_eq!(cpu.regs.pc, 0x0106);
+}
+
+#[test]
+fn rst75_latch_cleared_by_sim() {
+    // MVI A,10H; SIM; EI; NOP; NOP
+    let (mut cpu, mut mem) = machine(&[0x3E, 0x10, 0x30, 0xFB, 0x00, 0x00]);
+    cpu.rst75();
+    for _ in 0..5 {
+        step(&mut cpu, &mut mem);
+    }
+    assert_eq!(cpu.regs.pc, 0x0106);
+}
+
+#[test]
+fn rst75_latch_is_serviced() {
+    // MVI A,08H; SIM; EI; NOP; NOP
+    let (mut cpu, mut mem) = machine(&[0x3E, 0x08, 0x30, 0xFB, 0x00, 0x00]);
+    cpu.rst75();
+    for _ in 0..5 {
+        step(&mut cpu, &mut mem);
+    }
+    assert_eq!(cpu.regs.pc, 0x003C);
+}
+
+#[test]
+fn trap_ignores_inte_and_rim_reports_previous_ie() {
+    // EI; NOP; RIM at 0024H
+    let (mut cpu, mut mem) = machine(&[0xFB, 0x00]);
+    mem[0x0024] = 0x20;
+    step(&mut cpu, &mut mem);
+    step(&mut cpu, &mut mem);
+    cpu.trap();
+    step(&mut cpu, &mut mem);
+    assert_eq!(cpu.regs.pc, 0x0024);
+    assert!(!cpu.interrupt_enabled);
+    step(&mut cpu, &mut mem);
+    assert_eq!(cpu.regs.a & 0x08, 0x08);
+}
+
+#[test]
+fn trap_wakes_from_halt_with_interrupts_disabled() {
+    let (mut cpu, mut mem) = machine(&[0x76]);
+    step(&mut cpu, &mut mem);
+    assert!(cpu.is_halted());
+    cpu.trap();
+    step(&mut cpu, &mut mem);
+    assert!(!cpu.is_halted());
+    assert_eq!(cpu.regs.pc, 0x0024);
+}
+
+#[test]
+fn dsub_arhl_rdel() {
+    // DSUB; ARHL; RDEL
+    let (mut cpu, mut mem) = machine(&[0x08, 0x10, 0x18]);
+    cpu.regs.set_rp(0x1234, 0x20);
+    cpu.regs.set_rp(0x1235, 0x00);
+    cpu.regs.set_rp(0x8001, 0x10);
+    assert_eq!(step(&mut cpu, &mut mem), 10);
+    assert_eq!(cpu.regs.get_rp(0x20), 0xFFFF);
+    assert!(cpu.regs.f.get_carry());
+    assert_eq!(step(&mut cpu, &mut mem), 7);
+    assert_eq!(cpu.regs.get_rp(0x20), 0xFFFF);
+    assert!(cpu.regs.f.get_carry());
+    assert_eq!(step(&mut cpu, &mut mem), 10);
+    assert_eq!(cpu.regs.get_rp(0x10), 0x0003);
+    assert!(cpu.regs.f.get_carry());
+    assert!(cpu.v_flag());
+}
+
+#[test]
+fn ldhi_ldsi_shlx_lhlx() {
+    // LDHI 10H; SHLX; LDSI 02H; LHLX
+    let (mut cpu, mut mem) = machine(&[0x28, 0x10, 0xD9, 0x38, 0x02, 0xED]);
+    cpu.regs.set_rp(0x2000, 0x20);
+    mem[0x8002] = 0xCD;
+    mem[0x8003] = 0xAB;
+    step(&mut cpu, &mut mem);
+    assert_eq!(cpu.regs.get_rp(0x10), 0x2010);
+    step(&mut cpu, &mut mem);
+    assert_eq!((mem[0x2010], mem[0x2011]), (0x00, 0x20));
+    step(&mut cpu, &mut mem);
+    assert_eq!(cpu.regs.get_rp(0x10), 0x8002);
+    step(&mut cpu, &mut mem);
+    assert_eq!(cpu.regs.get_rp(0x20), 0xABCD);
+}
+
+#[test]
+fn builder_registers_leave_v_clear() {
+    use i8080_core::cpu::regs::Registers;
+    let mut mem = [0u8; 0x10000];
+    mem[0x0100] = 0xCB; // RSTV
+    let regs = Registers { pc: 0x0100, sp: 0x8000, ..Registers::default() };
+    let mut cpu = CPU::builder().variant(Variant::I8085).registers(regs).build();
+    cpu.regs.f.set(0);
+    assert!(!cpu.v_flag());
+    step(&mut cpu, &mut mem);
+    assert_eq!(cpu.regs.pc, 0x0101);
+}
+
+#[test]
+fn overflow_drives_rstv_and_k_drives_jk() {
+    // MVI A,7FH; ADI 01H; RSTV
+    let (mut cpu, mut mem) = machine(&[0x3E, 0x7F, 0xC6, 0x01, 0xCB]);
+    step(&mut cpu, &mut mem);
+    step(&mut cpu, &mut mem);
+    assert!(cpu.v_flag());
+    // S and V are both set, so K = S xor V is clear.
+    assert!(!cpu.k_flag());
+    assert_eq!(step(&mut cpu, &mut mem), 12);
+    assert_eq!(cpu.regs.pc, 0x0040);
+
+    // INX B from FFFFH sets K; JK 0300H; JNK is not taken.
+    let (mut cpu, mut mem) = machine(&[0x03, 0xDD, 0x00, 0x02, 0xFD, 0x00, 0x03]);
+    cpu.regs.set_rp(0xFFFF, 0x00);
+    step(&mut cpu, &mut mem);
+    assert!(cpu.k_flag());
+    assert_eq!(step(&mut cpu, &mut mem), 7);
+    assert_eq!(step(&mut cpu, &mut mem), 10);
+    assert_eq!(cpu.regs.pc, 0x0300);
+}
+
+#[test]
+fn ana_always_sets_aux_carry() {
+    // ANI 0F0H with A = 0F0H: no bit 3 on either side, yet AC is set on the 8085.
+    let (mut cpu, mut mem) = machine(&[0xE6, 0xF0]);
+    cpu.regs.a = 0xF0;
+    step(&mut cpu, &mut mem);
+    assert!(cpu.regs.f.get_aux());
+}
+
+/// Flat memory logging the machine cycles reported to `Bus::cycle`.
+struct Cycles {
+    mem: [u8; 0x10000],
+    log: Vec<(u32, Status, u16)>,
+}
+impl Bus for Cycles {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.mem[addr as usize]
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        self.mem[addr as usize] = val;
+    }
+    fn cycle(&mut self, c: MachineCycle) -> u8 {
+        self.log.push((c.t_state, c.status, c.addr));
+        0
+    }
+}
+
+#[test]
+fn rstv_and_jnk_report_their_cycles() {
+    // JNK 1234H with K set (not taken); RSTV with V set; RSTV with V clear
+    let (mut cpu, _) = machine(&[]);
+    let mut bus = Cycles { mem: [0; 0x10000], log: Vec::new() };
+    bus.mem[0x0100..0x0104].copy_from_slice(&[0xDD, 0x34, 0x12, 0xCB]);
+    bus.mem[0x0040] = 0xCB;
+    cpu.set_psw(0x22);
+    assert_eq!(cpu.next(&mut bus, &mut Ports::new()), 7);
+    assert_eq!(bus.log, [(0, Status::FETCH, 0x0100), (4, Status::MEMORY_READ, 0x0101)]);
+    bus.log.clear();
+    assert_eq!(cpu.next(&mut bus, &mut Ports::new()), 12);
+    assert_eq!(
+        bus.log,
+        [(0, Status::FETCH, 0x0103), (6, Status::STACK_WRITE, 0x7FFF), (9, Status::STACK_WRITE, 0x7FFE)]
+    );
+    bus.log.clear();
+    cpu.set_psw(0x00);
+    assert_eq!(cpu.next(&mut bus, &mut Ports::new()), 6);
+    assert_eq!(bus.log, [(0, Status::FETCH, 0x0040)]);
+}
@@ -0,0 +1,76 @@
+//! INTR requests: when they are taken and what the supplied instruction does.
+mod common;
+
+use i8080_core::cpu::io::Ports;
+use common::machine;
+
+#[test]
+fn request_waits_for_inte() {
+    let (mut cpu, mut mem) = machine(&[0x00, 0x00, 0xFB, 0x00, 0x00]); // NOP; NOP; EI; NOP; NOP
+    let mut io = Ports::new();
+    cpu.request_interrupt(&[0xFF]);
+    cpu.next(&mut mem, &mut io);
+    cpu.next(&mut mem, &mut io);
+    assert_eq!(cpu.regs.pc, 0x0102);
+    assert!(cpu.interrupt_pending());
+    cpu.next(&mut mem, &mut io); // EI
+    cpu.next(&mut mem, &mut io); // NOP
+    cpu.next(&mut mem, &mut io);
+    assert_eq!(cpu.regs.pc, 0x0038);
+    assert!(!cpu.interrupt_pending() && !cpu.interrupt_enabled);
+}
+
+#[test]
+fn call_pushes_interrupted_address() {
+    let (mut cpu, mut mem) = machine(&[0x00, 0x00]);
+    cpu.interrupt_enabled = true;
+    let mut io = Ports::new();
+    cpu.next(&mut mem, &mut io);
+    cpu.request_interrupt(&[0xCD, 0x00, 0x20]); // CALL 2000H
+    assert_eq!(cpu.next(&mut mem, &mut io), 17);
+    assert_eq!((cpu.regs.pc, cpu.regs.sp), (0x2000, 0x7FFE));
+    assert_eq!((mem[0x7FFE], mem[0x7FFF]), (0x01, 0x01));
+    // Memory around PC is not touched by the device bytes.
+    assert_eq!(&mem[0x00FE..0x0102], [0, 0, 0, 0]);
+}
+
+#[test]
+fn injected_instruction_reads_memory_past_its_length() {
+    // HL equals PC; of the bytes given only the one-byte opcode is the device's.
+    let (mut cpu, mut mem) = machine(&[0x00, 0x55]);
+    cpu.regs.h = 0x01;
+    cpu.regs.l = 0x01;
+    cpu.next(&mut mem, &mut Ports::new());
+    cpu.inject(&mut mem, &mut Ports::new(), &[0x7E, 0xAA, 0xBB]); // MOV A,M
+    assert_eq!((cpu.regs.a, cpu.regs.pc), (0x55, 0x0101));
+}
+
+#[test]
+fn ei_delays_acceptance_by_one_instruction() {
+    // EI; RET with a request already pending: RET still runs first.
+    let (mut cpu, mut mem) = machine(&[0xFB, 0xC9]);
+    mem[0x7FFE..0x8000].copy_from_slice(&[0x00, 0x02]);
+    cpu.regs.sp = 0x7FFE;
+    let mut io = Ports::new();
+    cpu.request_interrupt(&[0xFF]);
+    cpu.next(&mut mem, &mut io); // EI
+    assert!(cpu.interrupt_enabled && cpu.interrupt_pending());
+    assert_eq!(cpu.next(&mut mem, &mut io), 10); // RET, not the interrupt
+    assert_eq!((cpu.regs.pc, cpu.regs.sp), (0x0200, 0x8000));
+    assert!(cpu.interrupt_pending());
+    assert_eq!(cpu.next(&mut mem, &mut io), 11);
+    assert_eq!(cpu.regs.pc, 0x0038);
+    assert_eq!((mem[0x7FFE], mem[0x7FFF]), (0x00, 0x02));
+}
+
+#[test]
+fn di_after_ei_blocks_the_request() {
+    let (mut cpu, mut mem) = machine(&[0xFB, 0xF3, 0x00]); // EI; DI; NOP
+    let mut io = Ports::new();
+    cpu.request_interrupt(&[0xFF]);
+    for _ in 0..3 {
+        cpu.next(&mut mem, &mut io);
+    }
+    assert_eq!(cpu.regs.pc, 0x0103);
+    assert!(cpu.interrupt_pending());
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use i8080_core::cpu::io::{IoBus, Ports};
+use common::machine;
+
+#[test]
+fn ports_latch_inputs_and_strobe_outputs() {
+    let (mut cpu, mut mem) = machine(&[0xDB, 0x10, 0x3C, 0xD3, 0x20]); // IN 10H; INR A; OUT 20H
+    let mut io = Ports::new();
+    io.set_input_n(0x10, 0x41);
+    assert_eq!(io.out_strobe, (false, 0, 0));
+    assert_eq!(cpu.next(&mut mem, &mut io), 10);
+    assert_eq!(cpu.regs.a, 0x41);
+    cpu.next(&mut mem, &mut io);
+    assert_eq!(cpu.next(&mut mem, &mut io), 10);
+    assert_eq!(io.out_strobe, (true, 0x20, 0x42));
+    // Unset inputs read as zero.
+    assert_eq!(io.read_port(0x11), 0);
+}
+
+/// A device answering every port with its number and logging writes.
+#[derive(Default)]
+struct Device {
+    reads: Vec<u8>,
+    writes: Vec<(u8, u8)>,
+}
+impl IoBus for Device {
+    fn read_port(&mut self, port: u8) -> u8 {
+        self.reads.push(port);
+        port ^ 0xFF
+    }
+    fn write_port(&mut self, port: u8, value: u8) {
+        self.writes.push((port, value));
+    }
+}
+
+#[test]
+fn custom_io_bus() {
+    let (mut cpu, mut mem) = machine(&[0xDB, 0x05, 0xD3, 0x07, 0xDB, 0x06]); // IN 5; OUT 7; IN 6
+    let mut io = Device::default();
+    for _ in 0..3 {
+        cpu.next(&mut mem, &mut io);
+    }
+    assert_eq!(io.reads, [5, 6]);
+    assert_eq!(io.writes, [(7, 0xFA)]);
+    assert_eq!(cpu.regs.a, 0xF9);
+}
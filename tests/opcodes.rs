@@ -0,0 +1,185 @@
+use i8080_core::cpu::io::Ports;
+use i8080_core::cpu::CPU;
+
+// Intel 8080 states per opcode, with conditional CALL/RET not taken.
+#[rustfmt::skip]
+const CYCLES: [u8; 0x100] = [
+//  0   1   2   3   4   5   6   7   8   9   A   B   C   D   E   F
+    4, 10,  7,  5,  5,  5,  7,  4,  4, 10,  7,  5,  5,  5,  7,  4, // 0
+    4, 10,  7,  5,  5,  5,  7,  4,  4, 10,  7,  5,  5,  5,  7,  4, // 1
+    4, 10, 16,  5,  5,  5,  7,  4,  4, 10, 16,  5,  5,  5,  7,  4, // 2
+    4, 10, 13,  5, 10, 10, 10,  4,  4, 10, 13,  5,  5,  5,  7,  4, // 3
+    5,  5,  5,  5,  5,  5,  7,  5,  5,  5,  5,  5,  5,  5,  7,  5, // 4
+    5,  5,  5,  5,  5,  5,  7,  5,  5,  5,  5,  5,  5,  5,  7,  5, // 5
+    5,  5,  5,  5,  5,  5,  7,  5,  5,  5,  5,  5,  5,  5,  7,  5, // 6
+    7,  7,  7,  7,  7,  7,  7,  7,  5,  5,  5,  5,  5,  5,  7,  5, // 7
+    4,  4,  4,  4,  4,  4,  7,  4,  4,  4,  4,  4,  4,  4,  7,  4, // 8
+    4,  4,  4,  4,  4,  4,  7,  4,  4,  4,  4,  4,  4,  4,  7,  4, // 9
+    4,  4,  4,  4,  4,  4,  7,  4,  4,  4,  4,  4,  4,  4,  7,  4, // A
+    4,  4,  4,  4,  4,  4,  7,  4,  4,  4,  4,  4,  4,  4,  7,  4, // B
+    5, 10, 10, 10, 11, 11,  7, 11,  5, 10, 10, 10, 11, 17,  7, 11, // C
+    5, 10, 10, 10, 11, 11,  7, 11,  5, 10, 10, 10, 11, 17,  7, 11, // D
+    5, 10, 10, 18, 11, 11,  7, 11,  5,  5, 10,  4, 11, 17,  7, 11, // E
+    5, 10, 10,  4, 11, 11,  7, 11,  5,  5, 10,  4, 11, 17,  7, 11, // F
+];
+
+fn step(op: u8, flags: u8) -> (CPU, [u8; 0x10000], u32) {
+    let mut mem = [0u8; 0x10000];
+    mem[0x0100] = op;
+    mem[0x0101] = 0x00;
+    mem[0x0102] = 0x02;
+    mem[0x8000] = 0x34;
+    mem[0x8001] = 0x12;
+    let mut cpu = CPU::new(None, None);
+    cpu.regs.pc = 0x0100;
+    cpu.regs.sp = 0x8000;
+    cpu.regs.h = 0x03;
+    cpu.regs.f.set(flags);
+    let cycles = cpu
+        .try_step(&mut mem, &mut Ports::new())
+        .unwrap_or_else(|e| panic!("opcode {:02X}: {}", op, e))
+        .cycles;
+    (cpu, mem, cycles)
+}
+
+fn conditional(op: u8) -> bool {
+    op & 0xC7 == 0xC0 || op & 0xC7 == 0xC4
+}
+
+#[test]
+fn every_opcode_executes_with_hardware_cycles() {
+    for op in 0..=0xFFu8 {
+        // All flags clear: NZ, NC, PO and P hold.
+        let taken = conditional(op) && op & 0x08 == 0;
+        let expected = CYCLES[op as usize] + if taken { 6 } else { 0 };
+        assert_eq!(step(op, 0x02).2, expected as u32, "opcode {:02X}, flags clear", op);
+        // All flags set: Z, C, PE and M hold.
+        let taken = conditional(op) && op & 0x08 != 0;
+        let expected = CYCLES[op as usize] + if taken { 6 } else { 0 };
+        assert_eq!(step(op, 0xD7).2, expected as u32, "opcode {:02X}, flags set", op);
+    }
+}
+
+#[test]
+fn nop_aliases() {
+    for op in [0x00, 0x08, 0x10, 0x18, 0x20, 0x28, 0x30, 0x38] {
+        let (cpu, _, _) = step(op, 0x02);
+        assert_eq!(cpu.regs.pc, 0x0101, "opcode {:02X}", op);
+    }
+}
+
+#[test]
+fn jmp_alias() {
+    for op in [0xC3, 0xCB] {
+        let (cpu, _, _) = step(op, 0x02);
+        assert_eq!(cpu.regs.pc, 0x0200, "opcode {:02X}", op);
+    }
+}
+
+#[test]
+fn ret_alias() {
+    for op in [0xC9, 0xD9] {
+        let (cpu, _, _) = step(op, 0x02);
+        assert_eq!(cpu.regs.pc, 0x1234, "opcode {:02X}", op);
+        assert_eq!(cpu.regs.sp, 0x8002, "opcode {:02X}", op);
+    }
+}
+
+#[test]
+fn call_aliases() {
+    for op in [0xCD, 0xDD, 0xED, 0xFD] {
+        let (cpu, mem, _) = step(op, 0x02);
+        assert_eq!(cpu.regs.pc, 0x0200, "opcode {:02X}", op);
+        assert_eq!(cpu.regs.sp, 0x7FFE, "opcode {:02X}", op);
+        assert_eq!((mem[0x7FFF], mem[0x7FFE]), (0x01, 0x03), "opcode {:02X}", op);
+    }
+}
+
+fn exec(program: &[u8], setup: impl FnOnce(&mut CPU)) -> (CPU, [u8; 0x10000]) {
+    let mut mem = [0u8; 0x10000];
+    mem[0x0100..0x0100 + program.len()].copy_from_slice(program);
+    let mut cpu = CPU::new(None, None);
+    cpu.regs.pc = 0x0100;
+    cpu.regs.sp = 0x8000;
+    setup(&mut cpu);
+    let mut io = Ports::new();
+    for _ in 0..program.len() {
+        if cpu.regs.pc as usize >= 0x0100 + program.len() {
+            break;
+        }
+        cpu.try_step(&mut mem, &mut io).unwrap();
+    }
+    (cpu, mem)
+}
+
+#[test]
+fn conditional_call_pushes_return_address() {
+    let (cpu, mem) = exec(&[0xC4, 0x00, 0x02], |_| {}); // CNZ 0200H
+    assert_eq!(cpu.regs.pc, 0x0200);
+    assert_eq!((mem[0x7FFE], mem[0x7FFF]), (0x03, 0x01));
+}
+
+#[test]
+fn rar_rotates_through_carry() {
+    let (cpu, _) = exec(&[0x1F], |c| {
+        c.regs.a = 0x81;
+        c.regs.f.set_carry(false);
+    });
+    assert_eq!(cpu.regs.a, 0x40);
+    assert!(cpu.regs.f.get_carry());
+    let (cpu, _) = exec(&[0x1F], |c| {
+        c.regs.a = 0x02;
+        c.regs.f.set_carry(true);
+    });
+    assert_eq!(cpu.regs.a, 0x81);
+    assert!(!cpu.regs.f.get_carry());
+}
+
+#[test]
+fn inr_dcr_preserve_carry() {
+    for op in [0x3C, 0x3D] {
+        let (cpu, _) = exec(&[op], |c| c.regs.f.set_carry(true));
+        assert!(cpu.regs.f.get_carry(), "opcode {:02X}", op);
+    }
+    let (cpu, _) = exec(&[0x3D], |c| c.regs.a = 0x10); // DCR A
+    assert_eq!(cpu.regs.a, 0x0F);
+    assert!(!cpu.regs.f.get_aux());
+}
+
+#[test]
+fn daa_adjusts_bcd_sum() {
+    // 38H + 45H = 7DH, adjusted to 83H.
+    let (cpu, _) = exec(&[0xC6, 0x45, 0x27], |c| c.regs.a = 0x38);
+    assert_eq!(cpu.regs.a, 0x83);
+    assert!(!cpu.regs.f.get_carry());
+    // 99H + 01H = 9AH, adjusted to 00H with carry.
+    let (cpu, _) = exec(&[0xC6, 0x01, 0x27], |c| c.regs.a = 0x99);
+    assert_eq!(cpu.regs.a, 0x00);
+    assert!(cpu.regs.f.get_carry() && cpu.regs.f.get_zero());
+}
+
+#[test]
+fn subtract_aux_carry_is_inverted_half_borrow() {
+    for op in [&[0x90][..], &[0xD6, 0x01], &[0xB8], &[0xFE, 0x01]] {
+        // 10H - 01H borrows from bit 4: AC clear.
+        let (cpu, _) = exec(op, |c| {
+            c.regs.a = 0x10;
+            c.regs.b = 0x01;
+        });
+        assert!(!cpu.regs.f.get_aux(), "{:02X?}", op);
+        // 11H - 01H does not: AC set.
+        let (cpu, _) = exec(op, |c| {
+            c.regs.a = 0x11;
+            c.regs.b = 0x01;
+        });
+        assert!(cpu.regs.f.get_aux(), "{:02X?}", op);
+    }
+}
+
+#[test]
+fn ana_aux_carry_ors_bit_3() {
+    let (cpu, _) = exec(&[0xE6, 0x08], |c| c.regs.a = 0x00);
+    assert!(cpu.regs.f.get_aux());
+    let (cpu, _) = exec(&[0xE6, 0xF0], |c| c.regs.a = 0xF0);
+    assert!(!cpu.regs.f.get_aux());
+}
@@ -0,0 +1,218 @@
+#![cfg(feature = "alloc")]
+mod common;
+
+use i8080_core::cpu::io::{IoBus, Ports};
+use i8080_core::cpu::replay::{EventKind, Recording, Replay};
+use i8080_core::cpu::CPU;
+use common::load;
+
+const PROGRAM: [u8; 11] = [
+    0xFB, //             EI
+    0xDB, 0x01, //       LOOP: IN 1
+    0x80, //             ADD B
+    0x47, //             MOV B,A
+    0xD3, 0x02, //       OUT 2
+    0xC3, 0x01, 0x00, // JMP LOOP
+    0x00,
+];
+
+fn machine() -> (CPU, [u8; 0x10000]) {
+    let (cpu, mut mem) = load(CPU::new(None, None), 0, &PROGRAM);
+    mem[0x38..0x3B].copy_from_slice(&[0x0C, 0xFB, 0xC9]); // INR C; EI; RET
+    (cpu, mem)
+}
+
+/// A device whose input changes on every read, standing in for the host.
+struct Noisy(u8);
+impl IoBus for Noisy {
+    fn read_port(&mut self, port: u8) -> u8 {
+        self.0 = self.0.wrapping_mul(13).wrapping_add(port | 1);
+        self.0
+    }
+    fn write_port(&mut self, _port: u8, _value: u8) {}
+}
+
+fn record(steps: usize) -> (Recording, CPU, [u8; 0x10000]) {
+    let (mut cpu, mut mem) = machine();
+    let mut io = Noisy(7);
+    let mut recording = Recording::new();
+    for i in 0..steps {
+        if i % 17 == 5 {
+            cpu.request_interrupt(&[0xFF]); // RST 7
+        }
+        cpu.step_recording(&mut mem, &mut io, &mut recording);
+    }
+    (recording, cpu, mem)
+}
+
+#[test]
+fn replay_is_identical() {
+    let (recording, recorded, recorded_mem) = record(200);
+    let kinds = |f: fn(&EventKind) -> bool| recording.events.iter().filter(|e| f(&e.kind)).count();
+    assert!(kinds(|k| matches!(k, EventKind::Input { .. })) > 20);
+    assert!(kinds(|k| matches!(k, EventKind::Interrupt { .. })) > 5);
+
+    let (mut cpu, mut mem) = machine();
+    let mut io = Ports::new();
+    let mut replay = Replay::new(recording);
+    for _ in 0..200 {
+        cpu.step_replaying(&mut mem, &mut io, &mut replay).unwrap();
+    }
+    assert!(replay.is_finished());
+    assert_eq!(cpu.cycles(), recorded.cycles());
+    assert_eq!(format!("{:?}", cpu.regs), format!("{:?}", recorded.regs));
+    assert!(mem == recorded_mem);
+    // Outputs still reach the host during replay.
+    assert_eq!(io.out_strobe, (true, 2, cpu.regs.b));
+}
+
+#[test]
+fn changed_program_is_reported_at_first_divergence() {
+    let (recording, _, _) = record(100);
+    let (mut cpu, mut mem) = machine();
+    mem[0x02] = 0x03; // IN 3
+    let mut replay = Replay::new(recording.clone());
+    let first = recording.events[0];
+    let err = loop {
+        if let Err(e) = cpu.step_replaying(&mut mem, &mut Ports::new(), &mut replay) {
+            break e;
+        }
+    };
+    assert_eq!((err.pc, err.cycle), (0x0001, 4));
+    assert_eq!(err.expected, Some(first));
+    assert_eq!(replay.divergence(), Some(err));
+    assert!(!replay.is_finished());
+    // Stays reported.
+    assert_eq!(cpu.step_replaying(&mut mem, &mut Ports::new(), &mut replay), Err(err));
+}
+
+#[test]
+fn missed_interrupt_is_a_divergence() {
+    let (mut recording, _, _) = record(100);
+    let i = recording.events.iter().position(|e| matches!(e.kind, EventKind::Interrupt { .. })).unwrap();
+    let event = recording.events[i];
+    // Pretend the interrupt came at the very first step, before EI.
+    recording.events.insert(0, event);
+    recording.events[0].cycle = 0;
+    recording.events[0].pc = 0;
+    let (mut cpu, mut mem) = machine();
+    let err = cpu.step_replaying(&mut mem, &mut Ports::new(), &mut Replay::new(recording)).unwrap_err();
+    assert_eq!((err.pc, err.cycle), (0, 0));
+    assert!(!cpu.interrupt_pending());
+}
+
+#[test]
+fn running_past_the_recording() {
+    let (recording, _, _) = record(10);
+    let (mut cpu, mut mem) = machine();
+    let mut replay = Replay::new(recording);
+    let err = loop {
+        if let Err(e) = cpu.step_replaying(&mut mem, &mut Ports::new(), &mut replay) {
+            break e;
+        }
+    };
+    assert_eq!(err.expected, None);
+    assert_eq!(err.pc, 0x0001);
+    assert_eq!(err.to_string(), format!("replay diverged at 0001, cycle {}; recording exhausted", err.cycle));
+}
+
+#[test]
+fn injected_instructions_are_replayed() {
+    let (mut cpu, mut mem) = machine();
+    let mut io = Noisy(3);
+    let mut recording = Recording::new();
+    for i in 0..150 {
+        if i % 23 == 4 {
+            cpu.inject_recording(&mut mem, &mut io, &[0xDB, 0x05], &mut recording); // IN 5
+        } else {
+            cpu.step_recording(&mut mem, &mut io, &mut recording);
+        }
+    }
+    assert!(recording.events.iter().any(|e| matches!(e.kind, EventKind::Injected { .. })));
+
+    let (mut replayed, mut replayed_mem) = machine();
+    let mut replay = Replay::new(recording);
+    for _ in 0..150 {
+        replayed.step_replaying(&mut replayed_mem, &mut Ports::new(), &mut replay).unwrap();
+    }
+    assert!(replay.is_finished());
+    assert_eq!(replayed.cycles(), cpu.cycles());
+    assert_eq!(format!("{:?}", replayed.regs), format!("{:?}", cpu.regs));
+}
+
+#[test]
+fn interrupt_missing_from_recording_is_a_divergence() {
+    let (recording, _, _) = record(100);
+    let (mut cpu, mut mem) = machine();
+    let mut replay = Replay::new(recording);
+    for _ in 0..3 {
+        cpu.step_replaying(&mut mem, &mut Ports::new(), &mut replay).unwrap();
+    }
+    // Interrupts are enabled and the recording has none here.
+    let pc = cpu.regs.pc;
+    cpu.request_interrupt(&[0xD7]);
+    let err = cpu.step_replaying(&mut mem, &mut Ports::new(), &mut replay).unwrap_err();
+    assert_eq!(err.pc, pc);
+}
+
+#[cfg(feature = "i8085")]
+#[test]
+fn i8085_vectored_interrupts_are_replayed() {
+    use i8080_core::cpu::Variant;
+    fn machine() -> (CPU, [u8; 0x10000]) {
+        // MVI A,08H; SIM; EI; LOOP: IN 1; ADD B; MOV B,A; JMP LOOP
+        let program = [0x3E, 0x08, 0x30, 0xFB, 0xDB, 0x01, 0x80, 0x47, 0xC3, 0x04, 0x00];
+        let (cpu, mut mem) = load(CPU::with_variant(Variant::I8085), 0, &program);
+        for vector in [0x24, 0x2C, 0x34, 0x3C] {
+            mem[vector..vector + 3].copy_from_slice(&[0x0C, 0xFB, 0xC9]); // INR C; EI; RET
+        }
+        (cpu, mem)
+    }
+    let (mut cpu, mut mem) = machine();
+    let mut io = Noisy(5);
+    let mut recording = Recording::new();
+    for i in 0..300 {
+        match i % 40 {
+            7 => cpu.rst75(),
+            15 => cpu.set_rst55(true),
+            17 => cpu.set_rst55(false),
+            29 => cpu.trap(),
+            _ => {}
+        }
+        cpu.step_recording(&mut mem, &mut io, &mut recording);
+    }
+    let vectors: Vec<_> = recording
+        .events
+        .iter()
+        .filter_map(|e| match e.kind {
+            EventKind::Vectored { vector } => Some(vector),
+            _ => None,
+        })
+        .collect();
+    for vector in [0x24, 0x2C, 0x3C] {
+        assert!(vectors.contains(&vector), "{vector:04X} not recorded");
+    }
+
+    let (mut replayed, mut replayed_mem) = machine();
+    let mut replay = Replay::new(recording);
+    for _ in 0..300 {
+        replayed.step_replaying(&mut replayed_mem, &mut Ports::new(), &mut replay).unwrap();
+    }
+    assert!(replay.is_finished());
+    assert_eq!(replayed.cycles(), cpu.cycles());
+    assert_eq!(format!("{:?}", replayed.regs), format!("{:?}", cpu.regs));
+
+    // A pin the recording never saw driven is reported.
+    let (mut replayed, mut replayed_mem) = machine();
+    let mut replay = Replay::new(Recording::new());
+    replayed.trap();
+    assert!(replayed.step_replaying(&mut replayed_mem, &mut Ports::new(), &mut replay).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn recording_serializes() {
+    let (recording, _, _) = record(50);
+    let json = serde_json::to_string(&recording).unwrap();
+    assert_eq!(serde_json::from_str::<Recording>(&json).unwrap(), recording);
+}
@@ -0,0 +1,94 @@
+use i8080_core::cpu::builder::{CpuBuilder, PowerOn};
+use i8080_core::cpu::io::Ports;
+use i8080_core::cpu::regs::Registers;
+use i8080_core::cpu::{Variant, CPU};
+
+#[test]
+fn reset_clears_pc_inte_and_halt_only() {
+    let mut mem = [0u8; 0x10000];
+    mem[0x0200..0x0202].copy_from_slice(&[0xFB, 0x76]); // EI; HLT
+    let mut cpu = CPU::new(Some(0x0200), Some(0x8000));
+    cpu.regs.a = 0x12;
+    cpu.regs.h = 0x34;
+    let mut io = Ports::new();
+    cpu.next(&mut mem, &mut io);
+    cpu.next(&mut mem, &mut io);
+    assert!(cpu.interrupt_enabled && cpu.is_halted());
+    let cycles = cpu.cycles();
+    cpu.reset();
+    assert_eq!(cpu.regs.pc, 0);
+    assert!(!cpu.interrupt_enabled && !cpu.is_halted());
+    assert_eq!((cpu.regs.a, cpu.regs.h, cpu.regs.sp), (0x12, 0x34, 0x8000));
+    assert_eq!(cpu.cycles(), cycles);
+    // Runs from 0 again.
+    cpu.next(&mut mem, &mut io);
+    assert_eq!(cpu.regs.pc, 1);
+}
+
+#[test]
+fn reset_disables_interrupts() {
+    let mut mem = [0u8; 0x10000];
+    mem[0] = 0xFB; // EI
+    let mut cpu = CPU::new(None, None);
+    let mut io = Ports::new();
+    cpu.next(&mut mem, &mut io);
+    cpu.reset();
+    cpu.request_interrupt(&[0xFF]);
+    // EI runs again instead of the interrupt being taken.
+    cpu.next(&mut mem, &mut io);
+    assert_eq!(cpu.regs.pc, 1);
+    assert!(cpu.interrupt_pending());
+}
+
+#[test]
+fn new_applies_start_registers() {
+    let cpu = CPU::new(Some(0x0100), Some(0xF000));
+    assert_eq!((cpu.regs.pc, cpu.regs.sp), (0x0100, 0xF000));
+}
+
+#[test]
+fn builder() {
+    let cpu = CPU::builder().pc(0x0100).sp(0x8000).build();
+    assert_eq!((cpu.regs.pc, cpu.regs.sp, cpu.regs.a), (0x0100, 0x8000, 0));
+    assert_eq!(cpu.variant(), Variant::I8080);
+
+    let cpu = CpuBuilder::new().power_on(PowerOn::Filled(0xFF)).sp(0x8000).build();
+    let r = cpu.regs;
+    assert_eq!([r.a, r.b, r.c, r.d, r.e, r.h, r.l], [0xFF; 7]);
+    assert_eq!((r.pc, r.sp), (0, 0x8000));
+    // The fixed PSW bits still hold.
+    assert_eq!(r.f.get(), 0xD7);
+
+    let regs = Registers { b: 1, c: 2, pc: 0x1234, ..Registers::default() };
+    let cpu = CpuBuilder::new().power_on(PowerOn::Filled(0xAA)).registers(regs).build();
+    assert_eq!((cpu.regs.a, cpu.regs.b, cpu.regs.c, cpu.regs.pc), (0, 1, 2, 0x1234));
+}
+
+#[test]
+fn random_power_on_is_repeatable() {
+    let a = CpuBuilder::new().power_on(PowerOn::Random(42)).build().regs;
+    let b = CpuBuilder::new().power_on(PowerOn::Random(42)).build().regs;
+    let c = CpuBuilder::new().power_on(PowerOn::Random(43)).build().regs;
+    let bytes = |r: Registers| [r.a, r.b, r.c, r.d, r.e, r.h, r.l, r.f.get()];
+    assert_eq!(bytes(a), bytes(b));
+    assert_ne!(bytes(a), bytes(c));
+    assert_eq!(a.pc, 0);
+}
+
+#[cfg(feature = "i8085")]
+#[test]
+fn i8085_reset_masks_rst_inputs() {
+    let mut cpu = CPU::builder().variant(Variant::I8085).build();
+    assert_eq!(cpu.variant(), Variant::I8085);
+    // Power-on and RESET IN agree.
+    assert_eq!(cpu.interrupt_mask(), 0x07);
+    assert_eq!(CPU::with_variant(Variant::I8085).interrupt_mask(), 0x07);
+    // SIM with MSE set unmasks everything.
+    let mut mem = [0u8; 0x10000];
+    mem[0] = 0x30;
+    cpu.regs.a = 0x08;
+    cpu.next(&mut mem, &mut Ports::new());
+    assert_eq!(cpu.interrupt_mask(), 0);
+    cpu.reset();
+    assert_eq!(cpu.interrupt_mask(), 0x07);
+}
@@ -0,0 +1,92 @@
+//! RST in program memory versus RST (or anything else) supplied by a device.
+mod common;
+
+use i8080_core::cpu::io::Ports;
+use i8080_core::cpu::CPU;
+use common::machine;
+
+fn run_to_halt(cpu: &mut CPU, mem: &mut [u8; 0x10000]) {
+    let mut io = Ports::new();
+    for _ in 0..1000 {
+        if cpu.is_halted() {
+            return;
+        }
+        cpu.next(mem, &mut io);
+    }
+    panic!("no HLT, PC={:04X}", cpu.regs.pc);
+}
+
+#[test]
+fn in_stream_rst_pushes_next_address() {
+    let (mut cpu, mut mem) = machine(&[0xEF]); // RST 5
+    assert_eq!(cpu.next(&mut mem, &mut Ports::new()), 11);
+    assert_eq!(cpu.regs.pc, 0x0028);
+    assert_eq!((mem[0x7FFE], mem[0x7FFF]), (0x01, 0x01));
+}
+
+#[test]
+fn rst_7_as_a_syscall() {
+    // A debugger-style service routine at 38H counts calls in B and returns.
+    let (mut cpu, mut mem) = machine(&[0x06, 0x00, 0xFF, 0xFF, 0xFF, 0x76]); // MVI B,0; RST 7 x3; HLT
+    mem[0x38..0x3A].copy_from_slice(&[0x04, 0xC9]); // INR B; RET
+    run_to_halt(&mut cpu, &mut mem);
+    assert_eq!(cpu.regs.b, 3);
+    assert_eq!(cpu.regs.pc, 0x0106);
+    assert_eq!(cpu.regs.sp, 0x8000);
+}
+
+#[test]
+fn interrupt_rst_pushes_current_address() {
+    let (mut cpu, mut mem) = machine(&[0x00]);
+    cpu.interrupt_enabled = true;
+    cpu.request_interrupt(&[0xFF]);
+    cpu.next(&mut mem, &mut Ports::new());
+    assert_eq!(cpu.regs.pc, 0x0038);
+    assert_eq!((mem[0x7FFE], mem[0x7FFF]), (0x00, 0x01));
+}
+
+#[test]
+fn inject_ignores_inte_and_clears_it() {
+    let (mut cpu, mut mem) = machine(&[0x00]);
+    assert_eq!(cpu.inject(&mut mem, &mut Ports::new(), &[0xD7]), 11); // RST 2
+    assert_eq!(cpu.regs.pc, 0x0010);
+    assert_eq!((mem[0x7FFE], mem[0x7FFF]), (0x00, 0x01));
+    assert_eq!(cpu.cycles(), 11);
+
+    let (mut cpu, mut mem) = machine(&[0x00]);
+    cpu.interrupt_enabled = true;
+    cpu.inject(&mut mem, &mut Ports::new(), &[0xCD, 0x00, 0x20]); // CALL 2000H
+    assert!(!cpu.interrupt_enabled);
+    assert_eq!(cpu.regs.pc, 0x2000);
+    assert_eq!((mem[0x7FFE], mem[0x7FFF]), (0x00, 0x01));
+}
+
+#[test]
+fn injected_non_branch_leaves_pc() {
+    let (mut cpu, mut mem) = machine(&[0x00]);
+    cpu.inject(&mut mem, &mut Ports::new(), &[0x3E, 0x42]); // MVI A,42H
+    // The operand comes from the device too; memory at PC holds a NOP.
+    assert_eq!((cpu.regs.a, cpu.regs.pc), (0x42, 0x0100));
+}
+
+#[test]
+fn injected_rst_wakes_from_halt() {
+    let (mut cpu, mut mem) = machine(&[0x76, 0x3C, 0x76]); // HLT; INR A; HLT
+    mem[0x08] = 0xC9; // RET
+    run_to_halt(&mut cpu, &mut mem);
+    cpu.inject(&mut mem, &mut Ports::new(), &[0xCF]); // RST 1
+    assert!(!cpu.is_halted());
+    run_to_halt(&mut cpu, &mut mem);
+    // RET comes back past the first HLT and runs INR A.
+    assert_eq!((cpu.regs.a, cpu.regs.pc), (1, 0x0103));
+}
+
+#[test]
+#[allow(deprecated)]
+fn deprecated_rst_forwards_to_inject() {
+    let (mut cpu, mut mem) = machine(&[0x00]);
+    cpu.instruction = 0xEF; // RST 5
+    assert_eq!(cpu.rst(&mut mem, &mut Ports::new()), 11);
+    assert_eq!(cpu.regs.pc, 0x0028);
+    assert_eq!((mem[0x7FFE], mem[0x7FFF]), (0x00, 0x01));
+}
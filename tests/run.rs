@@ -0,0 +1,45 @@
+use i8080_core::cpu::error::CpuError;
+use i8080_core::cpu::io::Ports;
+use i8080_core::cpu::{RunOutcome, CPU};
+
+#[test]
+fn run_for_reports_overshoot() {
+    // NOPs (4 states) then a 10 state JMP 0000H.
+    let mut mem = [0u8; 0x10000];
+    mem[0x0003] = 0xC3;
+    let mut cpu = CPU::new(None, None);
+    let mut io = Ports::new();
+    // 3 NOPs = 12, then the JMP runs to 22: 2 past a budget of 20.
+    assert_eq!(cpu.run_for(&mut mem, &mut io, 20), RunOutcome::Budget { overshoot: 2 });
+    assert_eq!(cpu.cycles(), 22);
+    assert_eq!(cpu.run_for(&mut mem, &mut io, 0), RunOutcome::Budget { overshoot: 0 });
+    cpu.reset_cycles();
+    assert_eq!(cpu.cycles(), 0);
+}
+
+#[test]
+fn run_for_stops_on_halt_and_breakpoint() {
+    let mut mem = [0u8; 0x10000];
+    mem[0x0002] = 0x76; // HLT
+    let mut cpu = CPU::new(None, None);
+    let mut io = Ports::new();
+    cpu.set_breakpoint(0x0001);
+    assert_eq!(
+        cpu.run_for(&mut mem, &mut io, 1000),
+        RunOutcome::Stopped { cycles: 4, reason: CpuError::Breakpoint { pc: 1 } }
+    );
+    assert_eq!(
+        cpu.run_for(&mut mem, &mut io, 1000),
+        RunOutcome::Stopped { cycles: 11, reason: CpuError::Halted { pc: 3 } }
+    );
+}
+
+#[test]
+fn run_until_predicate() {
+    // INR A; JMP 0000H
+    let mut mem = [0u8; 0x10000];
+    mem[..4].copy_from_slice(&[0x3C, 0xC3, 0x00, 0x00]);
+    let mut cpu = CPU::new(None, None);
+    let outcome = cpu.run_until(&mut mem, &mut Ports::new(), |c| c.regs.a == 3);
+    assert_eq!(outcome, RunOutcome::Condition { cycles: 5 + 10 + 5 + 10 + 5 });
+}
@@ -0,0 +1,119 @@
+#![cfg(feature = "alloc")]
+mod common;
+
+use i8080_core::cpu::io::Ports;
+use i8080_core::cpu::snapshot::{Snapshot, SnapshotError, VERSION};
+use i8080_core::cpu::CPU;
+use common::load;
+
+/// Counts B down to zero, storing each value at 2000H, then halts.
+const PROGRAM: [u8; 11] = [
+    0x06, 0x05, //       MVI B,5
+    0x78, //             LOOP: MOV A,B
+    0x32, 0x00, 0x20, // STA 2000H
+    0x05, //             DCR B
+    0xC2, 0x02, 0x00, // JNZ LOOP
+    0x76, //             HLT
+];
+
+fn machine() -> (CPU, Vec<u8>) {
+    let (cpu, mem) = load(CPU::new(None, None), 0, &PROGRAM);
+    (cpu, mem.to_vec())
+}
+
+#[test]
+fn round_trip_resumes_identically() {
+    let (mut cpu, mut mem) = machine();
+    let mut io = Ports::new();
+    for _ in 0..7 {
+        cpu.next(&mut mem, &mut io);
+    }
+    cpu.set_breakpoint(0x0006);
+    cpu.request_interrupt(&[0xCD, 0x34, 0x12]);
+    let saved = Snapshot::new(&cpu, &mem).save();
+    let restored = Snapshot::load(&saved).unwrap();
+    assert_eq!(restored.save(), saved);
+    assert_eq!(restored.memory, mem);
+    assert_eq!(restored.cpu.cycles(), cpu.cycles());
+    assert!(restored.cpu.interrupt_pending());
+
+    let (mut a, mut mem_a) = (cpu, mem);
+    let (mut b, mut mem_b) = (restored.cpu, restored.memory);
+    a.clear_breakpoints();
+    b.clear_breakpoints();
+    while !a.is_halted() {
+        a.next(&mut mem_a, &mut io);
+        b.next(&mut mem_b, &mut io);
+    }
+    assert!(b.is_halted());
+    assert_eq!(a.cycles(), b.cycles());
+    assert_eq!(Snapshot::new(&a, &mem_a).save(), Snapshot::new(&b, &mem_b).save());
+}
+
+#[test]
+fn layout_is_little_endian_and_stable() {
+    let mut cpu = CPU::new(Some(0x1234), Some(0xABCD));
+    cpu.regs.a = 0x11;
+    cpu.interrupt_enabled = true;
+    let bytes = Snapshot::new(&cpu, &[0xAA, 0xBB]).save();
+    let mut want = vec![b'I', b'8', b'0', b'S', 0x01, 0x00, 0x00];
+    want.extend([0x11, 0, 0, 0, 0, 0, 0, 0x02]); // A..L, F with its fixed bit
+    want.extend([0xCD, 0xAB, 0x34, 0x12]); // SP, PC
+    want.push(0x00); // opcode
+    want.extend([0; 8]); // cycles
+    want.push(0x01); // INTE
+    want.extend([0; 4]); // no INTR request
+    want.push(8);
+    want.extend([0; 8 * 3]); // breakpoints
+    want.extend([0; 8]); // T-state, wait states
+    want.extend([0x07, 0, 0]); // 8085 state, RST 5.5-7.5 masked
+    want.extend([2, 0, 0, 0, 0xAA, 0xBB]);
+    assert_eq!(bytes, want);
+    assert_eq!(VERSION, 1);
+}
+
+#[test]
+fn rejects_bad_input() {
+    let (cpu, mem) = machine();
+    let good = Snapshot::new(&cpu, &mem[..16]).save();
+    assert_eq!(Snapshot::load(b"NOPE").unwrap_err(), SnapshotError::BadMagic);
+    let mut newer = good.clone();
+    newer[4] = 2;
+    assert_eq!(Snapshot::load(&newer).unwrap_err(), SnapshotError::UnsupportedVersion(2));
+    let mut variant = good.clone();
+    variant[6] = 9;
+    assert_eq!(Snapshot::load(&variant).unwrap_err(), SnapshotError::UnsupportedVariant(9));
+    assert_eq!(Snapshot::load(&good[..good.len() - 1]).unwrap_err(), SnapshotError::Truncated);
+    let mut long = good.clone();
+    long.push(0);
+    assert_eq!(Snapshot::load(&long).unwrap_err(), SnapshotError::TrailingBytes);
+}
+
+#[cfg(feature = "i8085")]
+#[test]
+fn i8085_state_round_trips() {
+    use i8080_core::cpu::Variant;
+    let mut cpu = CPU::with_variant(Variant::I8085);
+    cpu.reset();
+    cpu.set_rst55(true);
+    cpu.rst75();
+    let saved = Snapshot::new(&cpu, &[]).save();
+    let restored = Snapshot::load(&saved).unwrap();
+    assert_eq!(restored.cpu.variant(), Variant::I8085);
+    assert_eq!(restored.cpu.interrupt_mask(), 0x07);
+    assert_eq!(restored.save(), saved);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() {
+    let (mut cpu, mut mem) = machine();
+    cpu.next(&mut mem, &mut Ports::new());
+    let snap = Snapshot::new(&cpu, &mem[..0x20]);
+    let json = serde_json::to_string(&snap).unwrap();
+    let back: Snapshot = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.save(), snap.save());
+    let regs = serde_json::to_string(&cpu.regs).unwrap();
+    let regs: i8080_core::cpu::regs::Registers = serde_json::from_str(&regs).unwrap();
+    assert_eq!(regs.b, 5);
+}
@@ -0,0 +1,162 @@
+use i8080_core::cpu::bus::{Bus, MachineCycle};
+use i8080_core::cpu::error::CpuError;
+use i8080_core::cpu::io::Ports;
+use i8080_core::cpu::{StepInfo, CPU};
+
+#[test]
+fn try_step_reports_instruction() {
+    let mut mem = [0u8; 0x10000];
+    mem[0] = 0x3E; // MVI A,42H
+    mem[1] = 0x42;
+    let mut cpu = CPU::new(None, None);
+    let info = cpu.try_step(&mut mem, &mut Ports::new());
+    assert_eq!(info, Ok(StepInfo { pc: 0, opcode: 0x3E, cycles: 7 }));
+    assert_eq!(cpu.regs.a, 0x42);
+}
+
+#[test]
+fn breakpoint_stops_once_then_resumes() {
+    let mut mem = [0u8; 0x10000];
+    let mut io = Ports::new();
+    let mut cpu = CPU::new(None, None);
+    assert!(cpu.set_breakpoint(0x0001));
+    assert!(cpu.try_step(&mut mem, &mut io).is_ok());
+    assert_eq!(cpu.try_step(&mut mem, &mut io), Err(CpuError::Breakpoint { pc: 1 }));
+    assert_eq!(cpu.regs.pc, 0x0001);
+    assert!(cpu.try_step(&mut mem, &mut io).is_ok());
+    assert_eq!(cpu.regs.pc, 0x0002);
+}
+
+#[test]
+fn breakpoint_slots_are_bounded() {
+    let mut cpu = CPU::new(None, None);
+    for addr in 0..i8080_core::cpu::MAX_BREAKPOINTS as u16 {
+        assert!(cpu.set_breakpoint(addr));
+    }
+    assert!(!cpu.set_breakpoint(0x1000));
+    cpu.clear_breakpoint(0);
+    assert!(cpu.set_breakpoint(0x1000));
+}
+
+#[test]
+fn halt_is_reported_after_hlt_executes() {
+    let mut mem = [0u8; 0x10000];
+    mem[0] = 0x76; // HLT
+    let mut io = Ports::new();
+    let mut cpu = CPU::new(None, None);
+    assert_eq!(
+        cpu.try_step(&mut mem, &mut io),
+        Ok(StepInfo { pc: 0, opcode: 0x76, cycles: 7 })
+    );
+    assert_eq!(cpu.try_step(&mut mem, &mut io), Err(CpuError::Halted { pc: 1 }));
+    assert!(cpu.is_halted());
+}
+
+#[test]
+fn hold_stalls_until_released() {
+    let mut mem = [0u8; 0x10000];
+    mem[0] = 0x3C; // INR A
+    let mut io = Ports::new();
+    let mut cpu = CPU::new(None, None);
+    cpu.set_hold(true);
+    assert_eq!(cpu.next(&mut mem, &mut io), 1);
+    assert!(cpu.hlda());
+    assert_eq!(cpu.try_step(&mut mem, &mut io), Err(CpuError::Held { pc: 0 }));
+    assert_eq!((cpu.regs.pc, cpu.regs.a, cpu.cycles()), (0, 0, 2));
+    cpu.set_hold(false);
+    assert_eq!(cpu.try_step(&mut mem, &mut io), Ok(StepInfo { pc: 0, opcode: 0x3C, cycles: 5 }));
+    assert!(!cpu.hlda());
+    assert_eq!(cpu.regs.a, 1);
+}
+
+#[test]
+fn hold_defers_interrupts() {
+    let mut mem = [0u8; 0x10000];
+    let mut io = Ports::new();
+    let mut cpu = CPU::new(None, None);
+    cpu.interrupt_enabled = true;
+    cpu.request_interrupt(&[0xFF]);
+    cpu.set_hold(true);
+    cpu.next(&mut mem, &mut io);
+    assert!(cpu.interrupt_pending());
+    cpu.set_hold(false);
+    cpu.next(&mut mem, &mut io);
+    assert_eq!(cpu.regs.pc, 0x0038);
+}
+
+/// A DMA device that takes the bus for `len` states once the core reaches
+/// `at`, and copies one byte while it has it.
+struct Dma {
+    mem: [u8; 0x10000],
+    at: u32,
+    len: u32,
+    held: u32,
+    starts: Vec<u32>,
+}
+
+impl Bus for Dma {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.mem[addr as usize]
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        self.mem[addr as usize] = val;
+    }
+    fn hold(&mut self, t_state: u32) -> bool {
+        if t_state < self.at || self.held == self.len {
+            return false;
+        }
+        if self.held == 0 {
+            self.mem[0x4000] = self.mem[0x3000];
+        }
+        self.held += 1;
+        true
+    }
+    fn cycle(&mut self, c: MachineCycle) -> u8 {
+        self.starts.push(c.t_state);
+        0
+    }
+}
+
+fn dma(at: u32, len: u32) -> Dma {
+    Dma { mem: [0; 0x10000], at, len, held: 0, starts: Vec::new() }
+}
+
+#[test]
+fn hold_from_the_bus_stalls_between_machine_cycles() {
+    let mut bus = dma(8, 300);
+    bus.mem[..3].copy_from_slice(&[0xCD, 0x00, 0x20]); // CALL 2000H
+    bus.mem[0x3000] = 0xAA;
+    let mut cpu = CPU::new(None, None);
+    cpu.regs.sp = 0x8000;
+    assert_eq!(cpu.next(&mut bus, &mut Ports::new()), 17 + 300);
+    // The second operand read waits for the transfer; the first is done.
+    assert_eq!(bus.starts, [0, 5, 308, 311, 314]);
+    assert_eq!((bus.mem[0x4000], cpu.regs.pc, bus.mem[0x7FFE]), (0xAA, 0x2000, 0x03));
+    assert!(!cpu.hlda());
+    assert_eq!(cpu.cycles(), 317);
+}
+
+#[test]
+fn hold_from_the_bus_after_the_last_machine_cycle() {
+    let mut bus = dma(4, 10);
+    let mut cpu = CPU::new(None, None);
+    // A NOP has a single machine cycle; HOLD still gets in after it.
+    assert_eq!(cpu.next(&mut bus, &mut Ports::new()), 4 + 10);
+    assert_eq!(cpu.next(&mut bus, &mut Ports::new()), 4);
+    assert_eq!(bus.starts, [0, 0]);
+}
+
+#[test]
+fn resume_only_skips_the_breakpoint_it_stopped_at() {
+    let mut mem = [0u8; 0x10000];
+    let mut io = Ports::new();
+    let mut cpu = CPU::new(None, None);
+    cpu.set_breakpoint(0x0001);
+    cpu.set_breakpoint(0x0002);
+    cpu.next(&mut mem, &mut io);
+    assert_eq!(cpu.try_step(&mut mem, &mut io), Err(CpuError::Breakpoint { pc: 1 }));
+    // Stepping on with `next` must not use up the resume on another breakpoint.
+    cpu.next(&mut mem, &mut io);
+    assert_eq!(cpu.try_step(&mut mem, &mut io), Err(CpuError::Breakpoint { pc: 2 }));
+    assert_eq!(cpu.try_step(&mut mem, &mut io).map(|i| i.pc), Ok(2));
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use i8080_core::cpu::bus::Status;
+use i8080_core::cpu::io::Ports;
+use i8080_core::cpu::trace::{LineTracer, TraceRecord};
+use i8080_core::cpu::CPU;
+use common::machine;
+
+fn records(cpu: &mut CPU, mem: &mut [u8; 0x10000], io: &mut Ports, steps: usize) -> Vec<TraceRecord> {
+    let mut out = Vec::new();
+    for _ in 0..steps {
+        cpu.step_traced(mem, io, &mut |r: &TraceRecord| out.push(*r));
+    }
+    out
+}
+
+#[test]
+fn reference_lines() {
+    // LXI SP,0F000H; MVI A,12H; INR A
+    let (mut cpu, mut mem) = machine(&[0x31, 0x00, 0xF0, 0x3E, 0x12, 0x3C]);
+    let mut tracer = LineTracer(String::new());
+    for _ in 0..3 {
+        cpu.step_traced(&mut mem, &mut Ports::new(), &mut tracer);
+    }
+    assert_eq!(
+        tracer.0,
+        "PC: 0100, AF: 0002, BC: 0000, DE: 0000, HL: 0000, SP: 8000, CYC: 0\n\
+         PC: 0103, AF: 0002, BC: 0000, DE: 0000, HL: 0000, SP: F000, CYC: 10\n\
+         PC: 0105, AF: 1202, BC: 0000, DE: 0000, HL: 0000, SP: F000, CYC: 17\n"
+    );
+}
+
+#[test]
+fn call_record() {
+    let (mut cpu, mut mem) = machine(&[0xCD, 0x34, 0x12]);
+    cpu.regs.sp = 0x8000;
+    let r = records(&mut cpu, &mut mem, &mut Ports::new(), 1)[0];
+    assert_eq!((r.pc, r.opcode_bytes(), r.cycles, r.total_cycles), (0x0100, &[0xCD, 0x34, 0x12][..], 17, 0));
+    assert_eq!(r.instruction.to_string(), "CALL 1234H");
+    assert_eq!((r.before.sp, r.after.sp, r.after.pc), (0x8000, 0x7FFE, 0x1234));
+    let accesses: Vec<_> = r.accesses().iter().map(|a| (a.status, a.addr, a.value)).collect();
+    assert_eq!(
+        accesses,
+        [
+            (Status::FETCH, 0x0100, 0xCD),
+            (Status::MEMORY_READ, 0x0101, 0x34),
+            (Status::MEMORY_READ, 0x0102, 0x12),
+            (Status::STACK_WRITE, 0x7FFF, 0x01),
+            (Status::STACK_WRITE, 0x7FFE, 0x03),
+        ]
+    );
+}
+
+#[test]
+fn port_values() {
+    let (mut cpu, mut mem) = machine(&[0xDB, 0x07, 0xD3, 0x08]);
+    let mut io = Ports::new();
+    io.set_input_n(7, 0x5A);
+    let r = records(&mut cpu, &mut mem, &mut io, 2);
+    assert_eq!(r[0].accesses()[2].status, Status::INPUT_READ);
+    assert_eq!(r[0].accesses()[2].value, 0x5A);
+    assert_eq!(r[1].accesses()[2].status, Status::OUTPUT_WRITE);
+    assert_eq!(r[1].accesses()[2].value, 0x5A);
+}
+
+#[test]
+fn interrupt_bytes_come_from_device() {
+    let (mut cpu, mut mem) = machine(&[0x00]);
+    cpu.regs.sp = 0x8000;
+    cpu.interrupt_enabled = true;
+    cpu.request_interrupt(&[0xCD, 0x00, 0x20]);
+    let r = records(&mut cpu, &mut mem, &mut Ports::new(), 1)[0];
+    assert_eq!(r.opcode_bytes(), [0xCD, 0x00, 0x20]);
+    assert_eq!(r.instruction.to_string(), "CALL 2000H");
+    assert_eq!(r.accesses()[0].status, Status::INTERRUPT_ACK);
+    assert_eq!(r.accesses()[2].value, 0x20);
+    assert_eq!(r.after.pc, 0x2000);
+}
+
+#[test]
+fn idle_steps_are_not_reported() {
+    let (mut cpu, mut mem) = machine(&[0x76]); // HLT
+    let r = records(&mut cpu, &mut mem, &mut Ports::new(), 3);
+    assert_eq!(r.len(), 1);
+    assert_eq!(r[0].instruction.to_string(), "HLT");
+    assert_eq!(cpu.cycles(), 7 + 4 + 4);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn io_sink() {
+    use i8080_core::cpu::trace::IoTracer;
+    let (mut cpu, mut mem) = machine(&[0x00]);
+    let mut tracer = IoTracer(Vec::new());
+    cpu.step_traced(&mut mem, &mut Ports::new(), &mut tracer);
+    assert_eq!(tracer.0, b"PC: 0100, AF: 0002, BC: 0000, DE: 0000, HL: 0000, SP: 8000, CYC: 0\n");
+}
+
+#[cfg(feature = "i8085")]
+#[test]
+fn i8085_branch_not_taken_keeps_its_operand() {
+    use i8080_core::cpu::Variant;
+    // LXI B,0FFFFH; INX B (sets K); JNZ 1234H; CNZ 1234H; JNK 1234H, none taken
+    let program = [0x01, 0xFF, 0xFF, 0x03, 0xC2, 0x34, 0x12, 0xC4, 0x34, 0x12, 0xDD, 0x34, 0x12];
+    let (_, mut mem) = machine(&program);
+    let mut cpu = CPU::with_variant(Variant::I8085);
+    cpu.regs.pc = 0x0100;
+    cpu.regs.f.set_zero(true);
+    let r = records(&mut cpu, &mut mem, &mut Ports::new(), 5);
+    let text: Vec<_> = r[2..].iter().map(|r| r.instruction.to_string()).collect();
+    assert_eq!(text, ["JNZ 1234H", "CNZ 1234H", "JNK 1234H"]);
+    assert_eq!(r[2].opcode_bytes(), [0xC2, 0x34, 0x12]);
+    assert_eq!(r[4].after.pc, 0x010D);
+}
@@ -0,0 +1,124 @@
+use i8080_core::cpu::io::Ports;
+use i8080_core::cpu::CPU;
+
+fn run(mem: &mut [u8; 0x10000], pc: u16, sp: u16) -> CPU {
+    let mut cpu = CPU::new(None, None);
+    cpu.regs.pc = pc;
+    cpu.regs.sp = sp;
+    cpu.next(mem, &mut Ports::new());
+    cpu
+}
+
+#[test]
+fn pc_wraps_past_ffff() {
+    let mut mem = [0u8; 0x10000];
+    let cpu = run(&mut mem, 0xFFFF, 0);
+    assert_eq!(cpu.regs.pc, 0x0000);
+}
+
+#[test]
+fn immediate_operands_wrap() {
+    let mut mem = [0u8; 0x10000];
+    mem[0xFFFE] = 0x21; // LXI H
+    mem[0xFFFF] = 0x34;
+    mem[0x0000] = 0x12;
+    let cpu = run(&mut mem, 0xFFFE, 0);
+    assert_eq!(cpu.regs.get_rp(0x20), 0x1234);
+    assert_eq!(cpu.regs.pc, 0x0001);
+}
+
+#[test]
+fn push_from_sp_zero_wraps() {
+    let mut mem = [0u8; 0x10000];
+    mem[0x0100] = 0xC5; // PUSH B
+    let mut cpu = CPU::new(None, None);
+    cpu.regs.pc = 0x0100;
+    cpu.regs.b = 0xAB;
+    cpu.regs.c = 0xCD;
+    cpu.next(&mut mem, &mut Ports::new());
+    assert_eq!(cpu.regs.sp, 0xFFFE);
+    assert_eq!(mem[0xFFFF], 0xAB);
+    assert_eq!(mem[0xFFFE], 0xCD);
+}
+
+#[test]
+fn pop_across_ffff_wraps() {
+    let mut mem = [0u8; 0x10000];
+    mem[0x0100] = 0xD1; // POP D
+    mem[0xFFFF] = 0x78;
+    mem[0x0000] = 0x56;
+    let cpu = run(&mut mem, 0x0100, 0xFFFF);
+    assert_eq!(cpu.regs.get_rp(0x10), 0x5678);
+    assert_eq!(cpu.regs.sp, 0x0001);
+}
+
+#[test]
+fn call_at_top_of_memory_pushes_wrapped_return() {
+    let mut mem = [0u8; 0x10000];
+    mem[0xFFFD] = 0xCD; // CALL 0200H
+    mem[0xFFFE] = 0x00;
+    mem[0xFFFF] = 0x02;
+    let cpu = run(&mut mem, 0xFFFD, 0x0000);
+    assert_eq!(cpu.regs.pc, 0x0200);
+    assert_eq!(cpu.regs.sp, 0xFFFE);
+    assert_eq!(mem[0xFFFF], 0x00);
+    assert_eq!(mem[0xFFFE], 0x00);
+}
+
+#[test]
+fn ret_from_wrapped_stack() {
+    let mut mem = [0u8; 0x10000];
+    mem[0x0100] = 0xC9; // RET
+    mem[0xFFFF] = 0x34;
+    mem[0x0000] = 0x12;
+    let cpu = run(&mut mem, 0x0100, 0xFFFF);
+    assert_eq!(cpu.regs.pc, 0x1234);
+    assert_eq!(cpu.regs.sp, 0x0001);
+}
+
+#[test]
+fn lhld_and_shld_wrap_at_ffff() {
+    let mut mem = [0u8; 0x10000];
+    mem[0x0100] = 0x2A; // LHLD FFFFH
+    mem[0x0101] = 0xFF;
+    mem[0x0102] = 0xFF;
+    mem[0xFFFF] = 0x11;
+    mem[0x0000] = 0x22;
+    let cpu = run(&mut mem, 0x0100, 0);
+    assert_eq!(cpu.regs.get_rp(0x20), 0x2211);
+
+    mem[0x0100] = 0x22; // SHLD FFFFH
+    let mut cpu = CPU::new(None, None);
+    cpu.regs.pc = 0x0100;
+    cpu.regs.h = 0xBE;
+    cpu.regs.l = 0xEF;
+    cpu.next(&mut mem, &mut Ports::new());
+    assert_eq!(mem[0xFFFF], 0xEF);
+    assert_eq!(mem[0x0000], 0xBE);
+}
+
+#[test]
+fn xthl_at_ffff_wraps() {
+    let mut mem = [0u8; 0x10000];
+    mem[0x0100] = 0xE3; // XTHL
+    mem[0xFFFF] = 0x01;
+    mem[0x0000] = 0x02;
+    let mut cpu = CPU::new(None, None);
+    cpu.regs.pc = 0x0100;
+    cpu.regs.sp = 0xFFFF;
+    cpu.regs.h = 0xAA;
+    cpu.regs.l = 0xBB;
+    cpu.next(&mut mem, &mut Ports::new());
+    assert_eq!(cpu.regs.get_rp(0x20), 0x0201);
+    assert_eq!(mem[0xFFFF], 0xBB);
+    assert_eq!(mem[0x0000], 0xAA);
+}
+
+#[test]
+fn rst_with_sp_one_wraps() {
+    let mut mem = [0u8; 0x10000];
+    mem[0x0100] = 0xFF; // RST 7
+    let cpu = run(&mut mem, 0x0100, 0x0001);
+    assert_eq!(cpu.regs.pc, 0x0038);
+    assert_eq!(cpu.regs.sp, 0xFFFF);
+}